@@ -0,0 +1,33 @@
+use chrono::{Datelike, NaiveDate};
+
+pub static DAYS_IN_MONTH: f32 = 365.25 / 12.0;
+
+/// Move date by given number of calendar months, clamping the day to the length
+/// of the resulting month, e.g. 31st of March minus one month is 29th of February.
+pub fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months;
+    let year = total.div_euclid(12);
+    let month = total.rem_euclid(12) as u32 + 1;
+    let day = date.day().min(days_in_month(year, month));
+    NaiveDate::from_ymd(year, month, day)
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = if month == 12 {
+        NaiveDate::from_ymd(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(year, month + 1, 1)
+    };
+    (next - NaiveDate::from_ymd(year, month, 1)).num_days() as u32
+}
+
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd(date.year(), date.month(), 1)
+}
+
+/// Last day of the period of given number of months containing date, periods are
+/// aligned to the start of the year, e.g. for 3 months these are quarters.
+pub fn period_end(date: NaiveDate, months: u32) -> NaiveDate {
+    let month = (date.month0() / months + 1) * months;
+    NaiveDate::from_ymd(date.year(), month, days_in_month(date.year(), month))
+}
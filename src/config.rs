@@ -0,0 +1,167 @@
+use crate::format;
+use chrono::Duration;
+use clap::ArgMatches;
+use savings_calc::conversions::{Provider, RATES_ACCESS_KEY_VARIABLE, RATES_URL_VARIABLE};
+use savings_calc::parse;
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Defaults read from `$XDG_CONFIG_HOME/savings_calc/config.toml`, e.g.
+///
+/// ```toml
+/// data-file = "/home/me/savings.csv"
+/// currency = "GBP"
+/// format = "markdown"
+/// period = "3 months"
+///
+/// [rates]
+/// access-key = "secret"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Csv file used when none is passed
+    pub data_file: Option<String>,
+    /// Currency used when `--currency` is not passed
+    pub currency: Option<String>,
+    pub format: Option<String>,
+    pub locale: Option<String>,
+    pub color: Option<String>,
+    /// Period rolling averages are calculated over when none is passed
+    pub period: Option<String>,
+    pub rates: Rates,
+}
+
+/// Exchange rates provider settings
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rates {
+    pub url: Option<String>,
+    pub access_key: Option<String>,
+}
+
+/// Where a setting comes from, from the most important one
+#[derive(Debug)]
+pub enum Source {
+    Environment,
+    ConfigFile,
+    Default,
+}
+
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("savings_calc").join("config.toml"))
+}
+
+pub fn load() -> Result<Config, Box<dyn Error>> {
+    match path() {
+        Some(path) if path.exists() => Ok(toml::from_str(&fs::read_to_string(path)?)?),
+        _ => Ok(Config::default()),
+    }
+}
+
+impl Config {
+    /// Settings along with environment variables command line arguments fall back to
+    pub fn variables(&self) -> Vec<(&'static str, Option<&'static str>, Option<&String>)> {
+        vec![
+            (
+                "data-file",
+                Some("SAVINGS_CALC_DATA_FILE"),
+                self.data_file.as_ref(),
+            ),
+            (
+                "currency",
+                Some("SAVINGS_CALC_CURRENCY"),
+                self.currency.as_ref(),
+            ),
+            ("format", Some("SAVINGS_CALC_FORMAT"), self.format.as_ref()),
+            ("locale", Some("SAVINGS_CALC_LOCALE"), self.locale.as_ref()),
+            ("color", Some("SAVINGS_CALC_COLOR"), self.color.as_ref()),
+            ("period", None, self.period.as_ref()),
+            (
+                "rates.url",
+                Some(RATES_URL_VARIABLE),
+                self.rates.url.as_ref(),
+            ),
+            (
+                "rates.access-key",
+                Some(RATES_ACCESS_KEY_VARIABLE),
+                self.rates.access_key.as_ref(),
+            ),
+        ]
+    }
+
+    /// Effective value of every setting and where it comes from, access key is masked.
+    pub fn settings(&self) -> Vec<(&'static str, Option<String>, Source)> {
+        self.variables()
+            .into_iter()
+            .map(|(name, variable, value)| {
+                let (value, source) = match (variable.and_then(|v| env::var(v).ok()), value) {
+                    (Some(value), _) => (Some(value), Source::Environment),
+                    (None, Some(value)) => (Some(value.clone()), Source::ConfigFile),
+                    (None, None) => (None, Source::Default),
+                };
+                if variable == Some(RATES_ACCESS_KEY_VARIABLE) {
+                    (name, value.map(|_| "********".to_string()), source)
+                } else {
+                    (name, value, source)
+                }
+            })
+            .collect()
+    }
+
+    /// Currency used when neither `--currency` nor its environment variable is passed
+    pub fn currency(&self) -> Result<Option<parse::Currency>, Box<dyn Error>> {
+        self.currency
+            .as_deref()
+            .map(parse::parse_currency_from_str)
+            .transpose()
+    }
+
+    /// Rolling average period used when none is passed
+    pub fn period(&self) -> Result<Option<Duration>, Box<dyn Error>> {
+        self.period
+            .as_deref()
+            .map(parse::parse_duration_from_str)
+            .transpose()
+    }
+
+    /// Exchange rates provider, environment variables win over the config file
+    pub fn provider(&self) -> Provider {
+        let mut provider = Provider::default();
+        if let (None, Some(url)) = (env::var_os(RATES_URL_VARIABLE), &self.rates.url) {
+            provider.url = url.clone();
+        }
+        if let (None, Some(key)) = (
+            env::var_os(RATES_ACCESS_KEY_VARIABLE),
+            &self.rates.access_key,
+        ) {
+            provider.access_key = Some(key.clone());
+        }
+        provider
+    }
+
+    /// Fill output options passed neither on command line nor in environment with
+    /// values from the config file.
+    pub fn apply(
+        &self,
+        matches: &ArgMatches,
+        output: &mut format::Options,
+    ) -> Result<(), Box<dyn Error>> {
+        let unset = |arg: &str, variable: &str| {
+            matches.occurrences_of(arg) == 0 && env::var_os(variable).is_none()
+        };
+        if let (true, Some(value)) = (unset("format", "SAVINGS_CALC_FORMAT"), &self.format) {
+            output.format = value.parse()?;
+        }
+        if let (true, Some(value)) = (unset("color", "SAVINGS_CALC_COLOR"), &self.color) {
+            output.color = value.parse()?;
+        }
+        if let (true, Some(value)) = (unset("locale", "SAVINGS_CALC_LOCALE"), &self.locale) {
+            output.locale = value.parse()?;
+        }
+        Ok(())
+    }
+}
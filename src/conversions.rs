@@ -1,13 +1,35 @@
-use crate::parse::{Currency, Record, Records};
+use crate::parse::{Column, Currency, Record, Records};
 use chrono::NaiveDate;
 use futures::future::join_all;
 use reqwest::{Client, Url};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::env;
+
+/// Environment variables overriding the exchange rates provider
+pub static RATES_URL_VARIABLE: &str = "SAVINGS_CALC_RATES_URL";
+pub static RATES_ACCESS_KEY_VARIABLE: &str = "SAVINGS_CALC_RATES_ACCESS_KEY";
 
 static EBC_API_ADDR: &str = "https://api.exchangeratesapi.io";
 static DATE_FMT: &str = "%Y-%m-%d";
 
+/// Where exchange rates are fetched from
+#[derive(Debug, Clone)]
+pub struct Provider {
+    pub url: String,
+    pub access_key: Option<String>,
+}
+
+impl Default for Provider {
+    /// Exchange rates API, unless overridden by environment variables
+    fn default() -> Self {
+        Provider {
+            url: env::var(RATES_URL_VARIABLE).unwrap_or_else(|_| EBC_API_ADDR.into()),
+            access_key: env::var(RATES_ACCESS_KEY_VARIABLE).ok(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct Rates {
     rates: HashMap<String, f32>,
@@ -17,35 +39,37 @@ pub async fn get_conversions(
     records: Records,
     exchange_to: Currency,
     date: Option<NaiveDate>,
+    provider: &Provider,
 ) -> Result<Records, String> {
-    let client = Client::new();
+    let rates = get_rates(&records, &exchange_to, date, provider).await?;
+    convert(records, &rates, exchange_to)
+}
 
-    let rates = if let Some(date) = date {
-        vec![query(&client, &date, &exchange_to, &records.currencies).await?]
-    } else {
-        let futures = records
-            .records
-            .iter()
-            .map(|rec| query(&client, &rec.date, &exchange_to, &records.currencies));
-        join_all(futures)
-            .await
-            .into_iter()
-            .collect::<Result<Vec<Vec<f32>>, String>>()?
-    };
+/// Check there are rates for every column of every record.
+fn check_rates(records: &Records, rates: &[Vec<f32>]) -> Result<(), String> {
+    if rates.len() != records.records.len()
+        || rates.iter().any(|r| r.len() != records.currencies.len())
+    {
+        return Err("Exchange rates do not match records!".to_string());
+    }
+    Ok(())
+}
 
+/// Sum records into a single `exchange_to` column using rates from `get_rates`.
+pub fn convert(
+    records: Records,
+    rates: &[Vec<f32>],
+    exchange_to: Currency,
+) -> Result<Records, String> {
+    check_rates(&records, rates)?;
     let mut new_records = Vec::new();
 
     for (i, record) in records.records.iter().enumerate() {
-        let date_rates: &Vec<f32> = if rates.len() == 1 {
-            rates[0].as_ref()
-        } else {
-            rates[i].as_ref()
-        };
         let savings = record
             .savings
             .iter()
             .enumerate()
-            .map(|(i, s)| s / date_rates[i])
+            .map(|(j, s)| s / rates[i][j])
             .fold(0.0, |acc, x| acc + x);
 
         new_records.push(Record {
@@ -54,26 +78,100 @@ pub async fn get_conversions(
         })
     }
     Ok(Records {
-        records: new_records.clone(),
+        records: new_records,
+        columns: vec![Column::amount(&exchange_to)],
         currencies: vec![exchange_to],
         filepath: records.filepath,
     })
 }
 
+/// Like `convert` but instead of summing everything into one total keep a separate
+/// column for every source currency, e.g. `GBP from USD`.
+pub fn split_convert(
+    records: Records,
+    rates: &[Vec<f32>],
+    exchange_to: Currency,
+) -> Result<Records, String> {
+    check_rates(&records, rates)?;
+    let new_records = records
+        .records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| Record {
+            date: record.date,
+            savings: record
+                .savings
+                .iter()
+                .enumerate()
+                .map(|(j, s)| s / rates[i][j])
+                .collect(),
+        })
+        .collect();
+
+    Ok(Records {
+        records: new_records,
+        currencies: records
+            .currencies
+            .iter()
+            .map(|c| Currency::label(format!("{} from {}", exchange_to, c)))
+            .collect(),
+        columns: records
+            .currencies
+            .iter()
+            .map(|_| Column::amount(&exchange_to))
+            .collect(),
+        filepath: records.filepath,
+    })
+}
+
+/// Fetch exchange rates from every currency in records to `exchange_to`, one set
+/// of rates per record. If date is passed the same rates are used for every record.
+pub async fn get_rates(
+    records: &Records,
+    exchange_to: &Currency,
+    date: Option<NaiveDate>,
+    provider: &Provider,
+) -> Result<Vec<Vec<f32>>, String> {
+    let client = Client::new();
+
+    if let Some(date) = date {
+        let rates = query(&client, provider, &date, exchange_to, &records.currencies).await?;
+        Ok(records.records.iter().map(|_| rates.clone()).collect())
+    } else {
+        let futures = records.records.iter().map(|rec| {
+            query(
+                &client,
+                provider,
+                &rec.date,
+                exchange_to,
+                &records.currencies,
+            )
+        });
+        join_all(futures).await.into_iter().collect()
+    }
+}
+
 async fn query(
     client: &Client,
+    provider: &Provider,
     date: &NaiveDate,
     exchange_to: &Currency,
     currencies: &Vec<Currency>,
 ) -> Result<Vec<f32>, String> {
-    let url = Url::parse(EBC_API_ADDR)
-        .unwrap()
+    let mut addr = provider.url.clone();
+    if !addr.ends_with('/') {
+        addr.push('/');
+    }
+    let url = Url::parse(&addr)
+        .map_err(|err| format!("Invalid rates url {}: {:?}", addr, err))?
         .join(date.format(DATE_FMT).to_string().as_str())
-        .unwrap();
+        .map_err(|err| format!("Invalid rates url {}: {:?}", addr, err))?;
 
-    let res = client
-        .get(url)
-        .query(&[("base", exchange_to.to_string())])
+    let mut request = client.get(url).query(&[("base", exchange_to.to_string())]);
+    if let Some(key) = &provider.access_key {
+        request = request.query(&[("access_key", key)]);
+    }
+    let res = request
         .send()
         .await
         .map_err(|err| format!("Error querying ebc: {:?}", err))?;
@@ -83,8 +181,14 @@ async fn query(
         .await
         .map_err(|err| format!("Invalid json: {:?}", err))?;
 
-    Ok(currencies
+    currencies
         .iter()
-        .map(|curr| rates.rates[&curr.to_string()])
-        .collect())
+        .map(|curr| {
+            rates
+                .rates
+                .get(&curr.to_string())
+                .cloned()
+                .ok_or_else(|| format!("No {} to {} rate for {}!", curr, exchange_to, date))
+        })
+        .collect()
 }
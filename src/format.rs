@@ -1,11 +1,21 @@
-use crate::parse::Records;
+use crate::config::Source;
+use crate::locale::Locale;
 use chrono::{Date, Duration, NaiveDate, TimeZone, Utc};
 use clap::arg_enum;
 use float_ord::FloatOrd;
 use plotters::coord::IntoMonthly;
 use plotters::prelude::*;
-use prettytable::{cell, format, Cell, Row, Table};
+use prettytable::{format, Cell, Row, Table};
+use savings_calc::goals::Progress;
+use savings_calc::parse::{Band, Column, ColumnKind, Currency, Issue, Record, Records};
+use savings_calc::recurring::{Entry, Rule};
+use savings_calc::statistics::{
+    Anomaly, AnomalyKind, Breakdown, Growth, Risk, Runway, SavingsRate, Trendline,
+};
+use serde_json::{json, Map, Value};
+use std::error::Error;
 use std::io;
+use structopt::StructOpt;
 use tempfile::Builder;
 use webbrowser;
 
@@ -14,70 +24,1042 @@ arg_enum! {
     pub enum Format {
         Table,
         Graph,
+        Csv,
+        Markdown,
+        Html,
+        Json,
     }
 }
 
+arg_enum! {
+    #[derive(Debug, PartialEq)]
+    pub enum ColorChoice {
+        Auto,
+        Always,
+        Never,
+    }
+}
+
+impl ColorChoice {
+    /// Whether output should be colored, `Auto` only when stdout is a terminal
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Auto => atty::is(atty::Stream::Stdout),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Options {
+    /// Format of outputted data
+    #[structopt(long, env = "SAVINGS_CALC_FORMAT", possible_values = &Format::variants(), case_insensitive = true, default_value = "Table")]
+    pub format: Format,
+
+    /// Append summary rows (first, last, min, max, mean and change) for every column
+    #[structopt(long)]
+    pub summary: bool,
+
+    /// Add percentage change from the previous row next to every column
+    #[structopt(long)]
+    pub percent_change: bool,
+
+    /// Add every column share of the row total, meaningful only when all columns are in
+    /// the same currency, e.g. `converse --split`
+    #[structopt(long)]
+    pub share: bool,
+
+    /// Color increases green and decreases red, `auto` colors only when printing to terminal
+    #[structopt(long, env = "SAVINGS_CALC_COLOR", possible_values = &ColorChoice::variants(), case_insensitive = true, default_value = "Auto")]
+    pub color: ColorChoice,
+
+    /// Highlight rows in which any column changed by more than this amount
+    #[structopt(long, value_name = "AMOUNT")]
+    pub threshold_amount: Option<f32>,
+
+    /// Highlight rows in which any column changed by more than this percentage
+    #[structopt(long, value_name = "PERCENT")]
+    pub threshold_percent: Option<f32>,
+
+    /// Locale used to format numbers, e.g. `en_GB` or `de`, by default numbers are
+    /// printed without thousands separators
+    #[structopt(long, env = "SAVINGS_CALC_LOCALE", default_value = "C")]
+    pub locale: Locale,
+
+    /// Show currency symbols next to amounts
+    #[structopt(long)]
+    pub symbols: bool,
+}
+
+/// Single cell of a report, kept untyped until rendered so every output format can
+/// present it its own way.
+#[derive(Debug, Clone)]
+pub enum Field {
+    Empty,
+    Text(String),
+    Date(NaiveDate),
+    /// Amount of money, along with the currency it is expressed in if known
+    Amount(f32, Option<Currency>),
+    Percent(f32),
+}
+
+impl Field {
+    fn to_json(&self) -> Value {
+        match self {
+            Field::Empty => Value::Null,
+            Field::Text(text) => json!(text),
+            Field::Date(date) => json!(date.to_string()),
+            Field::Amount(amount, _) | Field::Percent(amount) if !amount.is_finite() => Value::Null,
+            // go through text to not leak f32 to f64 conversion noise into output
+            Field::Amount(amount, _) | Field::Percent(amount) => {
+                json!(amount.to_string().parse::<f64>().unwrap())
+            }
+        }
+    }
+
+    pub fn render(&self, locale: &Locale, symbols: bool) -> String {
+        match self {
+            Field::Empty => "-".to_string(),
+            Field::Text(text) => text.clone(),
+            Field::Date(date) => date.to_string(),
+            Field::Amount(amount, _) | Field::Percent(amount) if !amount.is_finite() => {
+                "-".to_string()
+            }
+            Field::Amount(amount, Some(currency)) if symbols => {
+                locale.format_money(*amount, currency)
+            }
+            Field::Amount(amount, Some(currency)) => {
+                locale.format_number(*amount, currency.minor_units())
+            }
+            Field::Amount(amount, None) => locale.format_number(*amount, 2),
+            Field::Percent(percent) => format!("{}%", locale.format_number(*percent, 2)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trend {
+    Flat,
+    Up,
+    Down,
+}
+
+impl Trend {
+    fn of(change: f32) -> Self {
+        if change > 0.0 {
+            Trend::Up
+        } else if change < 0.0 {
+            Trend::Down
+        } else {
+            Trend::Flat
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReportRow {
+    pub fields: Vec<Field>,
+    /// Direction of change for every field, used to color the output
+    pub trends: Vec<Trend>,
+    /// Row crossed one of the configured thresholds
+    pub highlight: bool,
+}
+
+impl From<Vec<Field>> for ReportRow {
+    fn from(fields: Vec<Field>) -> Self {
+        ReportRow {
+            trends: fields.iter().map(|_| Trend::Flat).collect(),
+            fields,
+            highlight: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub titles: Vec<String>,
+    pub rows: Vec<ReportRow>,
+}
+
 static COLORS: [RGBColor; 5] = [RED, BLACK, BLUE, CYAN, MAGENTA];
 
-pub fn present_results(records: Records, format: Format) {
-    match format {
-        Format::Table => print_table(records),
-        Format::Graph => plot_graph(records),
+pub fn present_results(records: Records, options: &Options) {
+    match options.format {
+        Format::Graph => plot_graph(records, None),
+        _ => present_report(build_report(&records, options), options),
     }
 }
 
-pub fn print_table(records: Records) {
+/// Present projected records, graph shows them as a dashed continuation of history.
+pub fn present_forecast(history: Records, forecast: Records, options: &Options) {
+    match options.format {
+        Format::Graph => plot_graph(history, Some(forecast)),
+        _ => present_results(forecast, options),
+    }
+}
+
+pub fn present_report(report: Report, options: &Options) {
+    let colored = options.color.enabled();
+    match options.format {
+        Format::Table | Format::Graph => print_table(report, options, colored),
+        Format::Csv => print_csv(report, options).unwrap(),
+        Format::Markdown => print_markdown(report, options),
+        Format::Html => print_html(report, options, colored),
+        Format::Json => print_json(report).unwrap(),
+    }
+}
+
+/// Turn records into a report, adding the extra columns and summary rows requested
+/// in options.
+pub fn build_report(records: &Records, options: &Options) -> Report {
+    let mut titles = vec!["Date".to_string()];
+    for currency in records.currencies.iter() {
+        titles.push(currency.to_string());
+        if options.percent_change {
+            titles.push(format!("{} %", currency));
+        }
+        if options.share {
+            titles.push(format!("{} share", currency));
+        }
+    }
+    if options.share {
+        titles.push("Total".to_string());
+    }
+    let columns = Columns {
+        deltas: records.columns.iter().map(|c| c.is_delta()).collect(),
+        units: records.columns.iter().map(|c| c.unit.clone()).collect(),
+        percents: records
+            .columns
+            .iter()
+            .map(|c| c.kind == ColumnKind::DeltaPercent)
+            .collect(),
+    };
+
+    let mut rows = vec![];
+    let mut previous: Option<&Vec<f32>> = None;
+    for record in records.records.iter() {
+        let changes = previous.map(|p| percent_changes(p, &record.savings));
+        let mut row = values_row(
+            Field::Date(record.date),
+            &record.savings,
+            previous.map(|p| p.as_slice()),
+            changes.clone(),
+            true,
+            &columns,
+            options,
+        );
+        row.highlight = exceeds_thresholds(
+            &record.savings,
+            previous.map(|p| p.as_slice()),
+            changes,
+            &columns.deltas,
+            options,
+        );
+        rows.push(row);
+        previous = Some(&record.savings);
+    }
+
+    if options.summary && !records.records.is_empty() {
+        rows.extend(summary_rows(&records.records, &columns, options));
+    }
+    Report { titles, rows }
+}
+
+pub fn growth_report(growth: Vec<Growth>) -> Report {
+    let titles = vec![
+        "Currency",
+        "Start",
+        "End",
+        "Start value",
+        "End value",
+        "CAGR",
+        "TWR",
+        "Annualized TWR",
+        "XIRR",
+    ];
+    let percent = |value: Option<f32>| value.map_or(Field::Empty, Field::Percent);
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: growth
+            .into_iter()
+            .map(|g| {
+                let unit = g.unit;
+                ReportRow::from(vec![
+                    Field::Text(g.currency.to_string()),
+                    Field::Date(g.start),
+                    Field::Date(g.end),
+                    Field::Amount(g.start_value, unit.clone()),
+                    Field::Amount(g.end_value, unit),
+                    Field::Percent(g.cagr),
+                    percent(g.twr),
+                    percent(g.annualized_twr),
+                    percent(g.xirr),
+                ])
+            })
+            .collect(),
+    }
+}
+
+pub fn savings_rate_report(rates: Vec<SavingsRate>) -> Report {
+    let titles = vec!["Date", "Column", "Income", "Saved", "Spent", "Savings rate"];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: rates
+            .into_iter()
+            .map(|r| {
+                let unit = r.unit;
+                let mut row = ReportRow::from(vec![
+                    Field::Date(r.date),
+                    Field::Text(r.currency.to_string()),
+                    Field::Amount(r.income, unit.clone()),
+                    Field::Amount(r.saved, unit.clone()),
+                    Field::Amount(r.income - r.saved, unit),
+                    r.rate.map_or(Field::Empty, Field::Percent),
+                ]);
+                row.trends[3] = Trend::of(r.saved);
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn runway_report(runway: Runway) -> Report {
+    let titles = vec![
+        "Date",
+        "Balance",
+        "Monthly spending",
+        "Runway months",
+        "FI number",
+        "Progress",
+        "Average monthly",
+        "Projected FI date",
+    ];
+    let unit = runway.unit;
+    let mut row = ReportRow::from(vec![
+        Field::Date(runway.date),
+        Field::Amount(runway.balance, unit.clone()),
+        Field::Amount(runway.monthly_spending, unit.clone()),
+        Field::Amount(runway.months, None),
+        Field::Amount(runway.fi_number, unit.clone()),
+        Field::Percent(runway.progress),
+        Field::Amount(runway.average_monthly, unit),
+        match runway.fi_date {
+            Some(date) => Field::Date(date),
+            None => Field::Text("never".to_string()),
+        },
+    ]);
+    row.trends[6] = Trend::of(runway.average_monthly);
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: vec![row],
+    }
+}
+
+pub fn anomalies_report(anomalies: &[Anomaly]) -> Report {
+    let titles = vec![
+        "Date", "Column", "Previous", "Value", "Change", "Typical", "Reason",
+    ];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: anomalies
+            .iter()
+            .map(|a| {
+                let unit = a.unit.clone();
+                let mut row = ReportRow::from(vec![
+                    Field::Date(a.date),
+                    Field::Text(a.currency.to_string()),
+                    Field::Amount(a.previous, unit.clone()),
+                    Field::Amount(a.value, unit.clone()),
+                    Field::Amount(a.value - a.previous, unit.clone()),
+                    a.typical.map_or(Field::Empty, |t| Field::Amount(t, unit)),
+                    Field::Text(
+                        match a.kind {
+                            AnomalyKind::Jump => "jump",
+                            AnomalyKind::SignFlip => "sign flip",
+                        }
+                        .to_string(),
+                    ),
+                ]);
+                row.trends[4] = Trend::of(a.value - a.previous);
+                row.highlight = true;
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn validation_report(issues: &[Issue]) -> Report {
+    let titles = vec!["Row", "Column", "Problem"];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: issues
+            .iter()
+            .map(|i| {
+                let mut row = ReportRow::from(vec![
+                    Field::Text(i.row.to_string()),
+                    i.column
+                        .map_or(Field::Empty, |c| Field::Text(c.to_string())),
+                    Field::Text(i.problem.to_string()),
+                ]);
+                row.highlight = !i.problem.fixable();
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn config_report(path: &str, settings: Vec<(&str, Option<String>, Source)>) -> Report {
+    let titles = vec!["Setting", "Value", "Source"];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: settings
+            .into_iter()
+            .map(|(name, value, source)| {
+                ReportRow::from(vec![
+                    Field::Text(name.to_string()),
+                    value.map_or(Field::Empty, Field::Text),
+                    Field::Text(
+                        match source {
+                            Source::Environment => "environment",
+                            Source::ConfigFile => path,
+                            Source::Default => "default",
+                        }
+                        .to_string(),
+                    ),
+                ])
+            })
+            .collect(),
+    }
+}
+
+pub fn risk_report(risk: Vec<Risk>) -> Report {
+    let titles = vec![
+        "Currency",
+        "Periods",
+        "Volatility",
+        "Annualized volatility",
+        "Max drawdown",
+        "Peak",
+        "Trough",
+        "Recovery",
+        "Longest under water",
+    ];
+    let date = |date: Option<NaiveDate>| date.map_or(Field::Empty, Field::Date);
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: risk
+            .into_iter()
+            .map(|r| {
+                let mut row = ReportRow::from(vec![
+                    Field::Text(r.currency.to_string()),
+                    Field::Text(r.periods.to_string()),
+                    Field::Percent(r.volatility),
+                    Field::Percent(r.annualized_volatility),
+                    Field::Percent(r.max_drawdown),
+                    date(r.peak),
+                    date(r.trough),
+                    match r.recovery {
+                        Some(recovery) => Field::Date(recovery),
+                        None if r.peak.is_some() => Field::Text("not yet".to_string()),
+                        None => Field::Empty,
+                    },
+                    Field::Text(format!("{} days", r.under_water.num_days())),
+                ]);
+                row.trends[4] = Trend::of(r.max_drawdown);
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn breakdown_report(breakdown: Vec<Breakdown>) -> Report {
+    let titles = vec![
+        "Date",
+        "Column",
+        "Change",
+        "Contribution",
+        "Growth",
+        "FX effect",
+    ];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: breakdown
+            .into_iter()
+            .map(|b| {
+                let unit = b.unit;
+                let mut row = ReportRow::from(vec![
+                    Field::Date(b.date),
+                    Field::Text(b.currency.to_string()),
+                    Field::Amount(b.change, unit.clone()),
+                    Field::Amount(b.contribution, unit.clone()),
+                    Field::Amount(b.growth, unit.clone()),
+                    Field::Amount(b.fx, unit),
+                ]);
+                row.trends = vec![
+                    Trend::Flat,
+                    Trend::Flat,
+                    Trend::of(b.change),
+                    Trend::Flat,
+                    Trend::of(b.growth),
+                    Trend::of(b.fx),
+                ];
+                row
+            })
+            .collect(),
+    }
+}
+
+/// Report of converted total changes split into change of savings at constant
+/// rates and currency revaluation, contributions are not considered.
+pub fn fx_report(breakdown: Vec<Breakdown>) -> Report {
+    let titles = vec!["Date", "Column", "Change", "Savings change", "Revaluation"];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: breakdown
+            .into_iter()
+            .map(|b| {
+                let unit = b.unit;
+                let savings = b.contribution + b.growth;
+                let mut row = ReportRow::from(vec![
+                    Field::Date(b.date),
+                    Field::Text(b.currency.to_string()),
+                    Field::Amount(b.change, unit.clone()),
+                    Field::Amount(savings, unit.clone()),
+                    Field::Amount(b.fx, unit),
+                ]);
+                row.trends = vec![
+                    Trend::Flat,
+                    Trend::Flat,
+                    Trend::of(b.change),
+                    Trend::of(savings),
+                    Trend::of(b.fx),
+                ];
+                row
+            })
+            .collect(),
+    }
+}
+
+/// Report when every column is expected to reach target, given trends fitted to
+/// the history.
+pub fn target_report(records: &Records, trends: &[Trendline], target: f32) -> Report {
+    let titles = vec!["Column", "Current", "Target", "Per month", "Estimated date"];
+    let last = &records.records[records.records.len() - 1];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: records
+            .currencies
+            .iter()
+            .zip(trends.iter())
+            .enumerate()
+            .map(|(i, (currency, trend))| {
+                let unit = records.columns[i].unit.clone();
+                ReportRow::from(vec![
+                    Field::Text(currency.to_string()),
+                    Field::Amount(last.savings[i], unit.clone()),
+                    Field::Amount(target, unit.clone()),
+                    Field::Amount(trend.slope * 365.25 / 12.0, unit),
+                    match trend.reaches(target, last.date, last.savings[i]) {
+                        Some(date) => Field::Date(date),
+                        None => Field::Text("never".to_string()),
+                    },
+                ])
+            })
+            .collect(),
+    }
+}
+
+pub fn goals_report(progress: Vec<Progress>) -> Report {
+    let titles = vec![
+        "Goal",
+        "Deadline",
+        "Target",
+        "Current",
+        "Progress",
+        "Required monthly",
+        "Average monthly",
+        "On track",
+    ];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: progress
+            .into_iter()
+            .map(|p| {
+                let unit = Some(p.goal.currency.clone());
+                let mut row = ReportRow::from(vec![
+                    Field::Text(p.goal.name),
+                    Field::Date(p.goal.deadline),
+                    Field::Amount(p.goal.amount, unit.clone()),
+                    Field::Amount(p.current, unit.clone()),
+                    Field::Percent(p.percent),
+                    p.required_monthly
+                        .map_or(Field::Empty, |r| Field::Amount(r, unit.clone())),
+                    Field::Amount(p.average_monthly, unit),
+                    Field::Text(if p.on_track { "yes" } else { "no" }.to_string()),
+                ]);
+                row.trends[7] = if p.on_track { Trend::Up } else { Trend::Down };
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn recurring_report(rules: &[Rule], today: NaiveDate) -> Report {
+    let titles = vec![
+        "Rule",
+        "Column",
+        "Amount",
+        "Day",
+        "Start",
+        "Last applied",
+        "Next",
+    ];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: rules
+            .iter()
+            .map(|r| {
+                let mut row = ReportRow::from(vec![
+                    Field::Text(r.name.clone()),
+                    Field::Text(r.value.currency.to_string()),
+                    Field::Amount(r.value.amount, Some(r.value.currency.clone())),
+                    Field::Text(r.day.to_string()),
+                    Field::Date(r.start),
+                    r.last_applied.map_or(Field::Empty, Field::Date),
+                    Field::Date(r.next(r.last_applied.unwrap_or(today).max(today))),
+                ]);
+                row.trends[2] = Trend::of(r.value.amount);
+                row
+            })
+            .collect(),
+    }
+}
+
+pub fn recurring_entries_report(entries: &[Entry]) -> Report {
+    let titles = vec!["Date", "Rule", "Column", "Amount", "Balance"];
+    Report {
+        titles: titles.into_iter().map(String::from).collect(),
+        rows: entries
+            .iter()
+            .map(|e| {
+                let unit = Some(e.currency.clone());
+                let mut row = ReportRow::from(vec![
+                    Field::Date(e.date),
+                    Field::Text(e.rule.clone()),
+                    Field::Text(e.currency.to_string()),
+                    Field::Amount(e.amount, unit.clone()),
+                    Field::Amount(e.balance, unit),
+                ]);
+                row.trends[3] = Trend::of(e.amount);
+                row
+            })
+            .collect(),
+    }
+}
+
+fn percent_changes(previous: &[f32], current: &[f32]) -> Vec<Option<f32>> {
+    previous
+        .iter()
+        .zip(current.iter())
+        .map(|(p, c)| {
+            if *p == 0.0 {
+                None
+            } else {
+                Some((c - p) / p.abs() * 100.0)
+            }
+        })
+        .collect()
+}
+
+/// Per column details of records used while building a report.
+struct Columns {
+    /// Whether column holds changes rather than balances
+    deltas: Vec<bool>,
+    /// Currency in which column amounts are expressed
+    units: Vec<Option<Currency>>,
+    /// Whether column holds percentages rather than amounts
+    percents: Vec<bool>,
+}
+
+/// Delta columns already hold a change, for the rest compare with the previous row.
+fn change(value: f32, i: usize, previous: Option<&[f32]>, deltas: &[bool]) -> Option<f32> {
+    if deltas[i] {
+        Some(value)
+    } else {
+        previous.map(|p| value - p[i])
+    }
+}
+
+fn exceeds_thresholds(
+    values: &[f32],
+    previous: Option<&[f32]>,
+    changes: Option<Vec<Option<f32>>>,
+    deltas: &[bool],
+    options: &Options,
+) -> bool {
+    values.iter().enumerate().any(|(i, value)| {
+        let amount = match (
+            options.threshold_amount,
+            change(*value, i, previous, deltas),
+        ) {
+            (Some(threshold), Some(change)) => change.abs() > threshold,
+            _ => false,
+        };
+        let percent = match (
+            options.threshold_percent,
+            changes.as_ref().and_then(|c| c[i]),
+        ) {
+            (Some(threshold), Some(change)) if !deltas[i] => change.abs() > threshold,
+            _ => false,
+        };
+        amount || percent
+    })
+}
+
+fn values_row(
+    label: Field,
+    values: &[f32],
+    previous: Option<&[f32]>,
+    changes: Option<Vec<Option<f32>>>,
+    with_share: bool,
+    columns: &Columns,
+    options: &Options,
+) -> ReportRow {
+    let deltas = &columns.deltas;
+    let total: f32 = values.iter().sum();
+    let mut row = ReportRow::from(vec![label]);
+    let mut push = |field, trend| {
+        row.fields.push(field);
+        row.trends.push(trend);
+    };
+    for (i, value) in values.iter().enumerate() {
+        push(
+            if columns.percents[i] {
+                Field::Percent(*value)
+            } else {
+                Field::Amount(*value, columns.units[i].clone())
+            },
+            change(*value, i, previous, deltas).map_or(Trend::Flat, Trend::of),
+        );
+        if options.percent_change {
+            match changes.as_ref().and_then(|c| c[i]) {
+                Some(change) => push(Field::Percent(change), Trend::of(change)),
+                None => push(Field::Empty, Trend::Flat),
+            }
+        }
+        if options.share {
+            if with_share && total != 0.0 {
+                push(Field::Percent(value / total * 100.0), Trend::Flat);
+            } else {
+                push(Field::Empty, Trend::Flat);
+            }
+        }
+    }
+    if options.share {
+        if with_share {
+            let trend = if deltas.iter().all(|d| *d) {
+                Trend::of(total)
+            } else {
+                previous.map_or(Trend::Flat, |p| Trend::of(total - p.iter().sum::<f32>()))
+            };
+            let unit = if columns.units.windows(2).all(|w| w[0] == w[1]) {
+                columns.units.first().cloned().flatten()
+            } else {
+                None
+            };
+            push(Field::Amount(total, unit), trend);
+        } else {
+            push(Field::Empty, Trend::Flat);
+        }
+    }
+    row
+}
+
+fn summary_rows(records: &[Record], columns: &Columns, options: &Options) -> Vec<ReportRow> {
+    let first = &records[0].savings;
+    let last = &records[records.len() - 1].savings;
+    let column = |i: usize| records.iter().map(move |r| r.savings[i]);
+
+    let min: Vec<f32> = (0..first.len())
+        .map(|i| column(i).fold(f32::INFINITY, f32::min))
+        .collect();
+    let max: Vec<f32> = (0..first.len())
+        .map(|i| column(i).fold(f32::NEG_INFINITY, f32::max))
+        .collect();
+    let mean: Vec<f32> = (0..first.len())
+        .map(|i| column(i).sum::<f32>() / records.len() as f32)
+        .collect();
+    let change: Vec<f32> = first.iter().zip(last.iter()).map(|(f, l)| l - f).collect();
+    let balances = Columns {
+        deltas: vec![false; first.len()],
+        units: columns.units.clone(),
+        percents: columns.percents.clone(),
+    };
+    let changes = Columns {
+        deltas: vec![true; first.len()],
+        units: columns.units.clone(),
+        percents: columns.percents.clone(),
+    };
+
+    let row = |label: &str, values: &[f32], with_share| {
+        values_row(
+            Field::Text(label.into()),
+            values,
+            None,
+            None,
+            with_share,
+            &balances,
+            options,
+        )
+    };
+    vec![
+        row("First", first, true),
+        row("Last", last, true),
+        row("Min", &min, false),
+        row("Max", &max, false),
+        row("Mean", &mean, true),
+        values_row(
+            Field::Text("Change".into()),
+            &change,
+            None,
+            Some(percent_changes(first, last)),
+            false,
+            &changes,
+            options,
+        ),
+    ]
+}
+
+fn table_style(trend: Trend, highlight: bool) -> String {
+    let mut spec = match trend {
+        Trend::Flat => "",
+        Trend::Up => "Fg",
+        Trend::Down => "Fr",
+    }
+    .to_string();
+    if highlight {
+        spec.push_str("bBy");
+    }
+    spec
+}
+
+pub fn print_table(report: Report, options: &Options, colored: bool) {
     let mut table = Table::new();
     table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-    let mut titles = vec![cell!("Date")];
-    titles.extend(
-        records
-            .currencies
+    table.set_titles(Row::new(
+        report
+            .titles
+            .iter()
+            .map(|t| Cell::new(t.as_str()))
+            .collect(),
+    ));
+    for row in report.rows {
+        let cells = row
+            .fields
+            .iter()
+            .zip(row.trends.iter())
+            .enumerate()
+            .map(|(i, (field, trend))| {
+                let text = field.render(&options.locale, options.symbols);
+                if colored {
+                    Cell::new(text.as_str()).style_spec(&table_style(*trend, row.highlight))
+                } else if row.highlight && i == 0 {
+                    Cell::new(format!("{} *", text).as_str())
+                } else {
+                    Cell::new(text.as_str())
+                }
+            })
+            .collect();
+        table.add_row(Row::new(cells));
+    }
+    if colored {
+        table.print_tty(true);
+    } else {
+        table.print(&mut io::stdout()).unwrap();
+    }
+}
+
+pub fn print_csv(report: Report, options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    wtr.write_record(&report.titles)?;
+    for row in report.rows {
+        wtr.write_record(
+            row.fields
+                .iter()
+                .map(|f| f.render(&options.locale, options.symbols)),
+        )?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Escape characters which would break a markdown table cell.
+fn markdown_escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+pub fn print_markdown(report: Report, options: &Options) {
+    println!(
+        "| {} |",
+        report
+            .titles
             .iter()
-            .map(|c| Cell::new(c.to_string().as_str())),
+            .map(|t| markdown_escape(t))
+            .collect::<Vec<_>>()
+            .join(" | ")
     );
-    table.set_titles(Row::new(titles));
-    for record in records.records {
-        let mut cells = vec![cell!(record.date)];
-        cells.extend(
-            record
-                .savings
+    println!(
+        "|{}",
+        report
+            .titles
+            .iter()
+            .enumerate()
+            .map(|(i, _)| if i == 0 { " --- |" } else { " ---: |" })
+            .collect::<String>()
+    );
+    for row in report.rows {
+        println!(
+            "| {} |",
+            row.fields
                 .iter()
-                .map(|c| Cell::new(format!("{:.2}", c).as_str())),
+                .map(|f| {
+                    let text = markdown_escape(&f.render(&options.locale, options.symbols));
+                    if row.highlight {
+                        format!("**{}**", text)
+                    } else {
+                        text
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" | ")
         );
-        table.add_row(Row::new(cells));
     }
-    table.printstd();
+}
+
+pub fn print_json(report: Report) -> Result<(), Box<dyn Error>> {
+    let rows: Vec<Map<String, Value>> = report
+        .rows
+        .iter()
+        .map(|row| {
+            report
+                .titles
+                .iter()
+                .cloned()
+                .zip(row.fields.iter().map(|f| f.to_json()))
+                .collect()
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn print_html(report: Report, options: &Options, colored: bool) {
+    println!("<table>");
+    println!("  <thead>");
+    println!(
+        "    <tr>{}</tr>",
+        report
+            .titles
+            .iter()
+            .map(|t| format!("<th>{}</th>", escape_html(t)))
+            .collect::<String>()
+    );
+    println!("  </thead>");
+    println!("  <tbody>");
+    for row in report.rows {
+        let cells = row
+            .fields
+            .iter()
+            .zip(row.trends.iter())
+            .map(|(field, trend)| {
+                let text = escape_html(&field.render(&options.locale, options.symbols));
+                match trend {
+                    Trend::Up if colored => format!("<td style=\"color: green\">{}</td>", text),
+                    Trend::Down if colored => format!("<td style=\"color: red\">{}</td>", text),
+                    _ => format!("<td>{}</td>", text),
+                }
+            })
+            .collect::<String>();
+        if row.highlight && colored {
+            println!(
+                "    <tr class=\"highlight\" style=\"background: yellow\">{}</tr>",
+                cells
+            );
+        } else if row.highlight {
+            println!("    <tr class=\"highlight\">{}</tr>", cells);
+        } else {
+            println!("    <tr>{}</tr>", cells);
+        }
+    }
+    println!("  </tbody>");
+    println!("</table>");
 }
 
 fn to_date(date: &NaiveDate) -> Date<Utc> {
     Utc.from_local_date(date).unwrap()
 }
 
-pub fn plot_graph(records: Records) {
+/// Split line into dashes, every segment between points is cut into pieces and
+/// every other piece is kept.
+fn dashed(points: &[(Date<Utc>, f64)]) -> Vec<Vec<(Date<Utc>, f64)>> {
+    let pieces = 8;
+    let mut dashes = vec![];
+    for segment in points.windows(2) {
+        let ((start, from), (end, to)) = (segment[0], segment[1]);
+        let length = (end - start).num_seconds();
+        let point = |k: i64| {
+            (
+                start + Duration::seconds(length * k / pieces),
+                from + (to - from) * k as f64 / pieces as f64,
+            )
+        };
+        for k in (0..pieces).step_by(2) {
+            dashes.push(vec![point(k), point(k + 1)]);
+        }
+    }
+    dashes
+}
+
+/// Index of the history column a projected column continues and its role, columns
+/// which are not projections continue the column at the same index.
+fn band_of(column: &Column, j: usize) -> (usize, Band) {
+    match column.kind {
+        ColumnKind::Projection(i, band) => (i, band),
+        _ => (j, Band::Center),
+    }
+}
+
+/// Plot records, if forecast is passed draw it as dashed continuation of matching
+/// columns, with area between lower and upper bounds shaded, e.g. `GBP Low` and
+/// `GBP High` around `GBP`.
+pub fn plot_graph(records: Records, forecast: Option<Records>) {
     let file = Builder::new().suffix(".png").tempfile().unwrap();
     let root = BitMapBackend::new(file.path().into(), (1024, 768)).into_drawing_area();
     root.fill(&WHITE).unwrap();
     let offset = Duration::weeks(4);
-    let start: Date<_> = to_date(&records.records[0].date) - offset;
-    let end: Date<_> = to_date(&records.records[records.records.len() - 1].date) + offset;
-    let max_val = records
-        .records
-        .iter()
-        .map(|r| r.savings.iter().map(|s| FloatOrd(*s)).max().unwrap())
-        .max()
-        .unwrap()
-        .0
-        * 1.04;
-
-    let min_val = records
+    let all_records: Vec<_> = records
         .records
         .iter()
-        .map(|r| r.savings.iter().map(|s| FloatOrd(*s)).min().unwrap())
-        .min()
-        .unwrap()
-        .0
-        * 0.95;
+        .chain(forecast.iter().flat_map(|f| f.records.iter()))
+        .collect();
+    let start: Date<_> = to_date(&all_records[0].date) - offset;
+    let end: Date<_> = to_date(&all_records[all_records.len() - 1].date) + offset;
+    let values = || {
+        all_records
+            .iter()
+            .flat_map(|r| r.savings.iter())
+            .filter(|s| s.is_finite())
+            .map(|s| FloatOrd(*s))
+    };
+    let max_val = values().max().unwrap().0 * 1.04;
+    let min_val = values().min().unwrap().0 * 0.95;
 
     let mut chart = ChartBuilder::on(&root)
         .margin(10)
@@ -92,7 +1074,9 @@ pub fn plot_graph(records: Records) {
         records.currencies.iter().map(|_| Vec::new()).collect();
     for record in records.records {
         for (i, saving) in record.savings.iter().enumerate() {
-            series[i].push((to_date(&record.date), *saving as f64));
+            if saving.is_finite() {
+                series[i].push((to_date(&record.date), *saving as f64));
+            }
         }
     }
 
@@ -108,6 +1092,56 @@ pub fn plot_graph(records: Records) {
             .label(records.currencies[i].to_string())
             .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style.clone()));
     }
+
+    if let Some(forecast) = forecast {
+        let column = |j: usize| -> Vec<(Date<Utc>, f64)> {
+            forecast
+                .records
+                .iter()
+                .map(|r| (to_date(&r.date), r.savings[j] as f64))
+                .collect()
+        };
+        for (j, meta) in forecast.columns.iter().enumerate() {
+            let (i, band) = band_of(meta, j);
+            let color = &COLORS[i % COLORS.len()];
+            match band {
+                Band::Center => {
+                    let style = ShapeStyle {
+                        color: color.to_rgba(),
+                        filled: false,
+                        stroke_width: 3,
+                    };
+                    chart
+                        .draw_series(
+                            dashed(&column(j))
+                                .into_iter()
+                                .map(|dash| PathElement::new(dash, style.clone())),
+                        )
+                        .unwrap();
+                }
+                Band::Low => {
+                    let high = forecast
+                        .columns
+                        .iter()
+                        .enumerate()
+                        .position(|(k, c)| band_of(c, k) == (i, Band::High));
+                    if let Some(k) = high {
+                        let mut area = column(j);
+                        area.extend(column(k).into_iter().rev());
+                        let style = ShapeStyle {
+                            color: color.mix(0.2),
+                            filled: true,
+                            stroke_width: 1,
+                        };
+                        chart
+                            .draw_series(std::iter::once(Polygon::new(area, style)))
+                            .unwrap();
+                    }
+                }
+                Band::High => {}
+            }
+        }
+    }
     chart
         .configure_series_labels()
         .background_style(&WHITE.mix(0.8))
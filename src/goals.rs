@@ -0,0 +1,143 @@
+use crate::calendar;
+use crate::parse::{self, Currency, Records};
+use crate::statistics;
+use chrono::NaiveDate;
+use simple_error::{bail, SimpleError};
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct Goal {
+    pub name: String,
+    pub amount: f32,
+    pub currency: Currency,
+    pub deadline: NaiveDate,
+    /// Columns counted toward the goal, all of them if empty
+    pub accounts: Vec<Currency>,
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    pub goal: Goal,
+    /// Current value of counted accounts in goal currency
+    pub current: f32,
+    pub percent: f32,
+    /// Saving per month needed to reach the goal by deadline, None if deadline passed
+    pub required_monthly: Option<f32>,
+    /// Average saving per month so far
+    pub average_monthly: f32,
+    pub on_track: bool,
+}
+
+/// Goals are stored next to the records file, e.g. in `savings.goals.csv`.
+pub fn load_goals(records: &Records) -> Result<Vec<Goal>, Box<dyn Error>> {
+    let path = parse::sidecar_path(&records.filepath, "goals");
+    if !Path::new(&path).exists() {
+        return Ok(vec![]);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut goals = vec![];
+    for result in rdr.records() {
+        let row = result?;
+        if row.len() != 5 {
+            bail!("Goal row has to have 5 columns, got {}!", row.len());
+        }
+        goals.push(Goal {
+            name: row[0].to_string(),
+            amount: row[1].parse()?,
+            currency: parse::parse_currency_from_str(&row[2])?,
+            deadline: NaiveDate::parse_from_str(&row[3], parse::DATE_FORMAT)?,
+            accounts: row[4]
+                .split_whitespace()
+                .map(parse::parse_column_from_str)
+                .collect::<Result<_, _>>()?,
+        });
+    }
+    Ok(goals)
+}
+
+pub fn save_goals(records: &Records, goals: &[Goal]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(parse::sidecar_path(&records.filepath, "goals"))?;
+    wtr.write_record(&["Name", "Amount", "Currency", "Deadline", "Accounts"])?;
+    for goal in goals {
+        wtr.write_record(&[
+            goal.name.clone(),
+            goal.amount.to_string(),
+            goal.currency.to_string(),
+            goal.deadline.format(parse::DATE_FORMAT).to_string(),
+            goal.accounts
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Keep only columns counted toward the goal.
+pub fn goal_records(records: &Records, goal: &Goal) -> Result<Records, SimpleError> {
+    if goal.accounts.is_empty() {
+        return Ok(records.clone());
+    }
+    let indexes = goal
+        .accounts
+        .iter()
+        .map(|a| {
+            records
+                .currencies
+                .iter()
+                .position(|c| c == a)
+                .ok_or_else(|| {
+                    SimpleError::new(format!("Unknown account {} in goal {}!", a, goal.name))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut result = records.clone();
+    result.currencies = indexes
+        .iter()
+        .map(|i| records.currencies[*i].clone())
+        .collect();
+    result.columns = indexes
+        .iter()
+        .map(|i| records.columns[*i].clone())
+        .collect();
+    for record in result.records.iter_mut() {
+        record.savings = indexes.iter().map(|i| record.savings[*i]).collect();
+    }
+    Ok(result)
+}
+
+/// Calculate progress of the goal, records have to be already converted into a
+/// single column in goal currency.
+pub fn calculate_progress(
+    goal: Goal,
+    total: Records,
+    today: NaiveDate,
+) -> Result<Progress, String> {
+    let current = total
+        .records
+        .last()
+        .and_then(|r| r.savings.first().cloned())
+        .ok_or_else(|| "No records to calculate progress from!".to_string())?;
+    let average_monthly = statistics::monthly_average(total)?;
+
+    let months_left = (goal.deadline - today).num_days() as f32 / calendar::DAYS_IN_MONTH;
+    let missing = (goal.amount - current).max(0.0);
+    let required_monthly = if missing == 0.0 {
+        Some(0.0)
+    } else if months_left > 0.0 {
+        Some(missing / months_left)
+    } else {
+        None
+    };
+    Ok(Progress {
+        current,
+        percent: current / goal.amount * 100.0,
+        on_track: required_monthly.map_or(false, |required| average_monthly >= required),
+        required_monthly,
+        average_monthly,
+        goal,
+    })
+}
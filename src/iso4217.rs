@@ -0,0 +1,227 @@
+/// Active ISO 4217 currencies along with number of minor unit digits and, for the
+/// common ones, symbol. Precious metals and other codes without minor units in the
+/// standard are formatted with 2 digits.
+static CURRENCIES: &[(&str, usize, Option<&str>)] = &[
+    ("AED", 2, None),
+    ("AFN", 2, Some("؋")),
+    ("ALL", 2, None),
+    ("AMD", 2, Some("֏")),
+    ("ANG", 2, None),
+    ("AOA", 2, None),
+    ("ARS", 2, None),
+    ("AUD", 2, Some("A$")),
+    ("AWG", 2, None),
+    ("AZN", 2, Some("₼")),
+    ("BAM", 2, None),
+    ("BBD", 2, None),
+    ("BDT", 2, Some("৳")),
+    ("BGN", 2, Some("лв")),
+    ("BHD", 3, None),
+    ("BIF", 0, None),
+    ("BMD", 2, None),
+    ("BND", 2, None),
+    ("BOB", 2, None),
+    ("BOV", 2, None),
+    ("BRL", 2, Some("R$")),
+    ("BSD", 2, None),
+    ("BTN", 2, None),
+    ("BWP", 2, None),
+    ("BYN", 2, None),
+    ("BZD", 2, None),
+    ("CAD", 2, Some("C$")),
+    ("CDF", 2, None),
+    ("CHE", 2, None),
+    ("CHF", 2, Some("Fr.")),
+    ("CHW", 2, None),
+    ("CLF", 4, None),
+    ("CLP", 0, None),
+    ("CNY", 2, Some("¥")),
+    ("COP", 2, None),
+    ("COU", 2, None),
+    ("CRC", 2, Some("₡")),
+    ("CUP", 2, None),
+    ("CVE", 2, None),
+    ("CZK", 2, Some("Kč")),
+    ("DJF", 0, None),
+    ("DKK", 2, Some("kr")),
+    ("DOP", 2, None),
+    ("DZD", 2, None),
+    ("EGP", 2, None),
+    ("ERN", 2, None),
+    ("ETB", 2, None),
+    ("EUR", 2, Some("€")),
+    ("FJD", 2, None),
+    ("FKP", 2, None),
+    ("GBP", 2, Some("£")),
+    ("GEL", 2, Some("₾")),
+    ("GHS", 2, Some("₵")),
+    ("GIP", 2, None),
+    ("GMD", 2, None),
+    ("GNF", 0, None),
+    ("GTQ", 2, None),
+    ("GYD", 2, None),
+    ("HKD", 2, Some("HK$")),
+    ("HNL", 2, None),
+    ("HTG", 2, None),
+    ("HUF", 2, Some("Ft")),
+    ("IDR", 2, Some("Rp")),
+    ("ILS", 2, Some("₪")),
+    ("INR", 2, Some("₹")),
+    ("IQD", 3, None),
+    ("IRR", 2, None),
+    ("ISK", 0, Some("kr")),
+    ("JMD", 2, None),
+    ("JOD", 3, None),
+    ("JPY", 0, Some("¥")),
+    ("KES", 2, None),
+    ("KGS", 2, None),
+    ("KHR", 2, None),
+    ("KMF", 0, None),
+    ("KPW", 2, None),
+    ("KRW", 0, Some("₩")),
+    ("KWD", 3, None),
+    ("KYD", 2, None),
+    ("KZT", 2, Some("₸")),
+    ("LAK", 2, None),
+    ("LBP", 2, None),
+    ("LKR", 2, None),
+    ("LRD", 2, None),
+    ("LSL", 2, None),
+    ("LYD", 3, None),
+    ("MAD", 2, None),
+    ("MDL", 2, None),
+    ("MGA", 2, None),
+    ("MKD", 2, None),
+    ("MMK", 2, None),
+    ("MNT", 2, Some("₮")),
+    ("MOP", 2, None),
+    ("MRU", 2, None),
+    ("MUR", 2, None),
+    ("MVR", 2, None),
+    ("MWK", 2, None),
+    ("MXN", 2, Some("MX$")),
+    ("MXV", 2, None),
+    ("MYR", 2, Some("RM")),
+    ("MZN", 2, None),
+    ("NAD", 2, None),
+    ("NGN", 2, Some("₦")),
+    ("NIO", 2, None),
+    ("NOK", 2, Some("kr")),
+    ("NPR", 2, None),
+    ("NZD", 2, Some("NZ$")),
+    ("OMR", 3, None),
+    ("PAB", 2, None),
+    ("PEN", 2, None),
+    ("PGK", 2, None),
+    ("PHP", 2, Some("₱")),
+    ("PKR", 2, None),
+    ("PLN", 2, Some("zł")),
+    ("PYG", 0, Some("₲")),
+    ("QAR", 2, None),
+    ("RON", 2, Some("lei")),
+    ("RSD", 2, None),
+    ("RUB", 2, Some("₽")),
+    ("RWF", 0, None),
+    ("SAR", 2, None),
+    ("SBD", 2, None),
+    ("SCR", 2, None),
+    ("SDG", 2, None),
+    ("SEK", 2, Some("kr")),
+    ("SGD", 2, Some("S$")),
+    ("SHP", 2, None),
+    ("SLE", 2, None),
+    ("SOS", 2, None),
+    ("SRD", 2, None),
+    ("SSP", 2, None),
+    ("STN", 2, None),
+    ("SVC", 2, None),
+    ("SYP", 2, None),
+    ("SZL", 2, None),
+    ("THB", 2, Some("฿")),
+    ("TJS", 2, None),
+    ("TMT", 2, None),
+    ("TND", 3, None),
+    ("TOP", 2, None),
+    ("TRY", 2, Some("₺")),
+    ("TTD", 2, None),
+    ("TWD", 2, Some("NT$")),
+    ("TZS", 2, None),
+    ("UAH", 2, Some("₴")),
+    ("UGX", 0, None),
+    ("USD", 2, Some("$")),
+    ("USN", 2, None),
+    ("UYI", 0, None),
+    ("UYU", 2, None),
+    ("UYW", 4, None),
+    ("UZS", 2, None),
+    ("VED", 2, None),
+    ("VES", 2, None),
+    ("VND", 0, Some("₫")),
+    ("VUV", 0, None),
+    ("WST", 2, None),
+    ("XAF", 0, None),
+    ("XAG", 2, None),
+    ("XAU", 2, None),
+    ("XCD", 2, None),
+    ("XCG", 2, None),
+    ("XDR", 2, None),
+    ("XOF", 0, None),
+    ("XPD", 2, None),
+    ("XPF", 0, None),
+    ("XPT", 2, None),
+    ("YER", 2, None),
+    ("ZAR", 2, Some("R")),
+    ("ZMW", 2, None),
+    ("ZWG", 2, None),
+];
+
+fn find(code: &str) -> Option<&'static (&'static str, usize, Option<&'static str>)> {
+    CURRENCIES.iter().find(|(c, _, _)| *c == code)
+}
+
+pub fn is_known(code: &str) -> bool {
+    find(code).is_some()
+}
+
+pub fn minor_units(code: &str) -> Option<usize> {
+    find(code).map(|(_, digits, _)| *digits)
+}
+
+pub fn symbol(code: &str) -> Option<&'static str> {
+    find(code).and_then(|(_, _, symbol)| *symbol)
+}
+
+/// Known code differing from the given one by a single edit, where swapping adjacent
+/// letters counts as one, e.g. `GBP` for `GPB`.
+pub fn closest(code: &str) -> Option<&'static str> {
+    CURRENCIES
+        .iter()
+        .map(|(c, _, _)| (distance(code, c), *c))
+        .filter(|(d, _)| *d <= 1)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Optimal string alignment distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
@@ -0,0 +1,29 @@
+//! Parse, store, convert and analyse savings kept in a CSV file with one column per
+//! currency, e.g.
+//!
+//! ```no_run
+//! use savings_calc::{conversions, parse, statistics, Currency};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let records = parse::parse_from_str("savings.csv")?;
+//! let provider = conversions::Provider::default();
+//! let total = conversions::get_conversions(records, Currency::new("GBP")?, None, &provider).await?;
+//! let growth = statistics::calculate_growth(total, None, None, None)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Functions return errors instead of panicking or exiting, the `savings_calc`
+//! binary is a thin command line layer on top of them.
+
+pub mod calendar;
+pub mod conversions;
+pub mod goals;
+pub mod iso4217;
+pub mod parse;
+pub mod recurring;
+pub mod simulation;
+pub mod statistics;
+
+pub use conversions::get_conversions;
+pub use parse::{Currency, Record, Records, Value};
@@ -0,0 +1,120 @@
+use savings_calc::parse::Currency;
+use simple_error::{bail, SimpleError};
+
+/// Conventions used to present numbers and amounts of money.
+#[derive(Debug, Clone)]
+pub struct Locale {
+    pub decimal: char,
+    pub group: Option<char>,
+    /// Currency symbol goes before the amount, e.g. `£12.00` vs `12,00 €`
+    pub symbol_first: bool,
+}
+
+impl std::str::FromStr for Locale {
+    type Err = SimpleError;
+
+    /// Accepts POSIX style names, e.g. `de`, `en_GB` or `pl_PL.UTF-8`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = value
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .replace('-', "_");
+        let mut parts = name.split('_');
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = parts.next().unwrap_or_default().to_uppercase();
+
+        let locale = match (language.as_str(), region.as_str()) {
+            ("c", _) | ("posix", _) => Locale {
+                decimal: '.',
+                group: None,
+                symbol_first: true,
+            },
+            (_, "CH") => Locale {
+                decimal: '.',
+                group: Some('\''),
+                symbol_first: true,
+            },
+            ("en", _) | ("ja", _) | ("zh", _) | ("ko", _) | ("he", _) | ("th", _) => Locale {
+                decimal: '.',
+                group: Some(','),
+                symbol_first: true,
+            },
+            ("de", _)
+            | ("es", _)
+            | ("it", _)
+            | ("nl", _)
+            | ("pt", _)
+            | ("da", _)
+            | ("id", _)
+            | ("tr", _)
+            | ("el", _)
+            | ("ro", _) => Locale {
+                decimal: ',',
+                group: Some('.'),
+                symbol_first: false,
+            },
+            ("fr", _)
+            | ("pl", _)
+            | ("cs", _)
+            | ("sk", _)
+            | ("ru", _)
+            | ("uk", _)
+            | ("sv", _)
+            | ("nb", _)
+            | ("fi", _)
+            | ("hu", _)
+            | ("bg", _)
+            | ("lt", _)
+            | ("lv", _)
+            | ("et", _) => Locale {
+                decimal: ',',
+                group: Some('\u{a0}'),
+                symbol_first: false,
+            },
+            _ => bail!("Unknown locale {}!", value),
+        };
+        Ok(locale)
+    }
+}
+
+impl Locale {
+    pub fn format_number(&self, value: f32, digits: usize) -> String {
+        let formatted = format!("{:.*}", digits, value.abs());
+        let mut parts = formatted.split('.');
+        let integer = parts.next().unwrap_or_default();
+
+        let mut result = String::new();
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(group) = self.group {
+                    result.push(group);
+                }
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = parts.next() {
+            result.push(self.decimal);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// Format amount using currency minor units and its symbol, falling back to
+    /// the currency code if symbol is not known.
+    pub fn format_money(&self, value: f32, currency: &Currency) -> String {
+        let number = self.format_number(value, currency.minor_units());
+        match (currency.symbol(), self.symbol_first) {
+            (Some(symbol), true) => match number.strip_prefix('-') {
+                Some(number) => format!("-{}{}", symbol, number),
+                None => format!("{}{}", symbol, number),
+            },
+            (None, true) => format!("{} {}", currency, number),
+            (Some(symbol), false) => format!("{} {}", number, symbol),
+            (None, false) => format!("{} {}", number, currency),
+        }
+    }
+}
@@ -1,14 +1,68 @@
-use chrono::{Duration, NaiveDate};
-use clap;
-use dialoguer::Confirm;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::{self, arg_enum};
+use dialoguer::{Confirm, Input};
 use std::collections::HashSet;
+use std::process;
 use structopt::StructOpt;
 use tokio;
 
-mod conversions;
+use savings_calc::{conversions, goals, parse, recurring, simulation, statistics};
+
+mod config;
 mod format;
-mod parse;
-mod statistics;
+mod locale;
+
+/// Command line spelling of a library enum with the same variants
+macro_rules! cli_enum {
+    ($name:ident { $($variant:ident),* $(,)? }) => {
+        arg_enum! {
+            #[derive(Debug, Clone, Copy)]
+            enum $name {
+                $($variant),*
+            }
+        }
+
+        impl From<$name> for statistics::$name {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => statistics::$name::$variant),*
+                }
+            }
+        }
+    };
+}
+
+cli_enum!(DeltaMode { Absolute, Percent });
+cli_enum!(DeltaInterval {
+    Entry,
+    Month,
+    Quarter,
+    Year
+});
+cli_enum!(Frequency {
+    Monthly,
+    Quarterly,
+    Yearly
+});
+cli_enum!(ResampleMethod {
+    Last,
+    Interpolate,
+    Nearest
+});
+cli_enum!(BucketAlignment { Anchor, Calendar });
+cli_enum!(ForecastMethod {
+    Linear,
+    RollingAverage
+});
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum AverageMethod {
+        Cumulative,
+        Simple,
+        Exponential,
+    }
+}
 
 #[derive(Debug, StructOpt)]
 #[structopt(about = "Simple script to parse and combine savings in multiple currencies")]
@@ -16,65 +70,378 @@ struct SavingsCalc {
     #[structopt(subcommand)]
     cmd: Command,
 
-    /// Format of outputted data
-    #[structopt(long, possible_values = &format::Format::variants(), case_insensitive = true, default_value = "Table")]
-    format: format::Format,
+    #[structopt(flatten)]
+    output: format::Options,
+}
+
+#[derive(Debug, StructOpt)]
+struct DeltaOptions {
+    /// Add deltas between entries
+    #[structopt(short = "D", long)]
+    delta: bool,
+
+    /// Whether deltas should be amounts or percentages
+    #[structopt(long, possible_values = &DeltaMode::variants(), case_insensitive = true, default_value = "Absolute")]
+    delta_mode: DeltaMode,
+
+    /// Interval over which deltas are calculated, `entry` compares adjacent entries
+    #[structopt(long, possible_values = &DeltaInterval::variants(), case_insensitive = true, default_value = "Entry")]
+    delta_interval: DeltaInterval,
+}
+
+impl DeltaOptions {
+    fn apply(&self, records: parse::Records) -> parse::Records {
+        if self.delta {
+            statistics::calculate_deltas(
+                records,
+                self.delta_mode.into(),
+                self.delta_interval.into(),
+            )
+        } else {
+            records
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct ResampleOptions {
+    /// Put records on a regular calendar, one record at the end of every period
+    #[structopt(long, possible_values = &Frequency::variants(), case_insensitive = true)]
+    resample: Option<Frequency>,
+
+    /// How values at the end of period are picked when resampling
+    #[structopt(long, possible_values = &ResampleMethod::variants(), case_insensitive = true, default_value = "Last")]
+    resample_method: ResampleMethod,
+}
+
+impl ResampleOptions {
+    fn apply(&self, records: parse::Records) -> parse::Records {
+        if let Some(frequency) = self.resample {
+            statistics::resample(records, frequency.into(), self.resample_method.into()).unwrap()
+        } else {
+            records
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct AnomalyOptions {
+    /// How many times bigger than the typical change a change has to be to look suspicious
+    #[structopt(long, default_value = "5")]
+    anomaly_threshold: f32,
+
+    /// Number of preceding entries the typical change is taken from
+    #[structopt(long, default_value = "12")]
+    anomaly_window: usize,
+}
+
+impl AnomalyOptions {
+    fn find(&self, records: &parse::Records) -> Vec<statistics::Anomaly> {
+        statistics::find_anomalies(records, self.anomaly_threshold, self.anomaly_window)
+    }
+}
+
+/// Reasons `add` refuses to write, exit code of each of them is distinct so scripts
+/// can react
+#[derive(Debug, Clone, Copy)]
+enum Refusal {
+    NewCurrency = 3,
+    ExistingDate = 4,
+    Suspicious = 5,
+    Declined = 6,
+}
+
+#[derive(Debug, StructOpt)]
+struct PromptOptions {
+    /// Accept all confirmations without prompting
+    #[structopt(short, long)]
+    yes: bool,
+
+    /// Fail instead of prompting for confirmation, implied when stdin is not a terminal.
+    /// Exit code is 3 for new currencies, 4 for existing date, 5 for suspicious changes and
+    /// 6 for declined interactive entry
+    #[structopt(long, conflicts_with = "yes")]
+    no_input: bool,
+}
+
+impl PromptOptions {
+    /// Ask for confirmation and exit with refusal code if it is not given
+    fn confirm(&self, prompt: &str, refusal: Refusal) {
+        if self.yes {
+            return;
+        }
+        let accepted = if self.no_input || !atty::is(atty::Stream::Stdin) {
+            eprintln!("{} Pass --yes to accept.", prompt);
+            false
+        } else {
+            Confirm::new().with_prompt(prompt).interact().unwrap()
+        };
+        if !accepted {
+            eprintln!("Aborting!");
+            process::exit(refusal as i32);
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct DataOptions {
+    /// Input csv file, `data-file` from the config file is used if not passed
+    #[structopt(long, env = "SAVINGS_CALC_DATA_FILE", value_name = "FILE")]
+    file: Option<String>,
+}
+
+impl DataOptions {
+    fn path(&self, config: &config::Config) -> String {
+        match self.file.as_ref().or(config.data_file.as_ref()) {
+            Some(path) => path.clone(),
+            None => clap::Error::with_description(
+                "No input file, pass --file or set data-file in the config file",
+                clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit(),
+        }
+    }
+
+    fn load(&self, config: &config::Config) -> parse::Records {
+        let path = self.path(config);
+        parse::parse_from_str(&path).unwrap_or_else(|err| {
+            clap::Error::value_validation_auto(format!("{}: {}", path, err)).exit()
+        })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct ContributionsOptions {
+    /// Csv file in the same layout as input file with money paid in (positive) or
+    /// taken out (negative) on given dates, they are not counted as returns,
+    /// `<input>.contributions.csv` is used if present
+    #[structopt(long, value_name = "FILE")]
+    contributions: Option<String>,
+}
+
+impl ContributionsOptions {
+    fn load(&self, records: &parse::Records) -> Option<parse::Records> {
+        parse::load_contributions(records, self.contributions.as_deref())
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct RealOptions {
+    /// Show balances in today's money
+    #[structopt(long)]
+    real: bool,
+
+    /// Show balances in money of given date, pass `today` for Today date
+    #[structopt(long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+    real_at: Option<NaiveDate>,
+
+    /// Csv file in the same layout as input file with consumer price index per currency,
+    /// `<input>.cpi.csv` is used if not passed
+    #[structopt(long, value_name = "FILE")]
+    cpi: Option<String>,
+}
+
+impl RealOptions {
+    fn apply(&self, records: parse::Records) -> parse::Records {
+        let base = match (self.real, self.real_at) {
+            (_, Some(base)) => base,
+            (true, None) => Utc::today().naive_local(),
+            (false, None) => return records,
+        };
+        let cpi = match parse::load_cpi(&records, self.cpi.as_deref())
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit())
+        {
+            Some(cpi) => cpi,
+            None => clap::Error::value_validation_auto(format!(
+                "No CPI data, pass --cpi or create {}",
+                parse::sidecar_path(&records.filepath, "cpi")
+            ))
+            .exit(),
+        };
+        statistics::deflate(records, &cpi, base)
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum RecurringCommand {
+    /// Show every rule along with its next date
+    Show {
+        #[structopt(flatten)]
+        data: DataOptions,
+    },
+    /// Add a new rule
+    Add {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Name of the rule
+        name: String,
+
+        /// Amount added to the column every month along with its name, e.g. 200GBP or
+        /// -50USD for money taken out
+        #[structopt(short, long, allow_hyphen_values = true)]
+        value: parse::Value,
+
+        /// Day of month, the last day is used in shorter months
+        #[structopt(short, long, default_value = "1")]
+        day: u32,
+
+        /// Date from which the rule applies, if nothing is passed today will be used
+        #[structopt(short, long, default_value = "today", value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_any_date_from_str))]
+        start: NaiveDate,
+    },
+    /// Remove a rule
+    Remove {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Name of the rule
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum GoalsCommand {
+    /// Show progress of every goal
+    Show {
+        #[structopt(flatten)]
+        data: DataOptions,
+    },
+    /// Add a new goal
+    Add {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Name of the goal
+        name: String,
+
+        /// Amount along with currency name, e.g. 10000GBP
+        #[structopt(short, long)]
+        value: parse::Value,
+
+        /// Date by which goal should be reached
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_deadline_from_str))]
+        deadline: NaiveDate,
+
+        /// Columns counted toward the goal, all of them if nothing is passed
+        #[structopt(short, long, parse(try_from_str = parse::parse_column_from_str))]
+        account: Vec<parse::Currency>,
+    },
+    /// Remove a goal
+    Remove {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Name of the goal
+        name: String,
+    },
 }
 
 #[derive(Debug, StructOpt)]
 enum Command {
     /// Add data to our savings spreadsheet
     Add {
-        /// Input csv file
-        #[structopt(parse(try_from_str = parse::parse_from_str))]
-        records: parse::Records,
+        #[structopt(flatten)]
+        data: DataOptions,
         /// Date of the entry, if nothing is passed today will be used
         #[structopt(short, long, default_value = "today", value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
         date: NaiveDate,
 
         /// Amount along with currency name, e.g. 123.45GBP
-        #[structopt(short, long, required = true)]
+        #[structopt(short, long, required_unless = "interactive")]
         value: Vec<parse::Value>,
 
+        /// Ask for the value of every column, the last one is kept if nothing is entered,
+        /// and confirm once before writing
+        #[structopt(short, long, conflicts_with_all = &["value", "no-input"])]
+        interactive: bool,
+
         /// Do not write file, only show what the result would look like
         #[structopt(long)]
         dry_run: bool,
+
+        #[structopt(flatten)]
+        anomaly: AnomalyOptions,
+
+        #[structopt(flatten)]
+        prompt: PromptOptions,
+    },
+    /// Check file for unsorted or duplicated dates, ragged rows and invalid values
+    Validate {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Sort rows and merge duplicated dates, later rows win
+        #[structopt(long)]
+        fix: bool,
+    },
+    /// Scan the whole file for suspicious changes, e.g. typos or sign flips
+    Check {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        #[structopt(flatten)]
+        anomaly: AnomalyOptions,
     },
     /// Parse our saving spreadsheet and display data
     Show {
-        /// Input csv file
-        #[structopt(parse(try_from_str = parse::parse_from_str))]
-        records: parse::Records,
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        #[structopt(flatten)]
+        real: RealOptions,
+
+        #[structopt(flatten)]
+        resample: ResampleOptions,
+
+        #[structopt(flatten)]
+        delta: DeltaOptions,
     },
     /// Parse and converse into other currencies
     Converse {
-        /// Input csv file
-        #[structopt(parse(try_from_str = parse::parse_from_str))]
-        records: parse::Records,
+        #[structopt(flatten)]
+        data: DataOptions,
 
         /// Exchange rate for date, pass `today` for Today date
         #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
         date: Option<NaiveDate>,
 
-        #[structopt(parse(try_from_str = parse::parse_currency_from_str))]
-        currency: parse::Currency,
+        /// Currency to converse into, `currency` from the config file is used if not passed
+        #[structopt(env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        #[structopt(flatten)]
+        real: RealOptions,
+
+        #[structopt(flatten)]
+        resample: ResampleOptions,
 
-        /// Add deltas between entries
-        #[structopt(short = "D", long)]
-        delta: bool,
+        #[structopt(flatten)]
+        delta: DeltaOptions,
+
+        /// Keep a separate column for every source currency instead of a single total
+        #[structopt(long)]
+        split: bool,
+
+        /// Instead of balances show how much of every change came from savings at constant
+        /// rates and how much from currency revaluation, per source currency
+        #[structopt(long, conflicts_with_all = &["split", "delta", "date"])]
+        fx_attribution: bool,
     },
     /// Calculate averages
     RollingAverage {
-        /// Input csv file
-        #[structopt(parse(try_from_str = parse::parse_from_str))]
-        records: parse::Records,
+        #[structopt(flatten)]
+        data: DataOptions,
 
-        /// Over what period rolling average should be calculated
-        #[structopt(default_value = "1 month", parse(try_from_str = parse::parse_duration_from_str))]
-        period: Duration,
+        /// Over what period rolling average should be calculated, `period` from the config
+        /// file or 1 month is used if not passed
+        #[structopt(parse(try_from_str = parse::parse_duration_from_str))]
+        period: Option<Duration>,
 
         /// Currency in which should averages be presented, if not passed due per currency averages
-        #[structopt(short, long, parse(try_from_str = parse::parse_currency_from_str))]
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
         currency: Option<parse::Currency>,
 
         /// Exchange rate for date, pass `today` for Today date
@@ -89,30 +456,381 @@ enum Command {
         #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
         end_date: Option<NaiveDate>,
 
-        /// Show rolling average split into buckets, note that if there is not enough data points
-        /// for given granurality results may be missing
+        /// Show rolling average split into buckets, buckets without enough data points are
+        /// reported with empty values
         #[structopt(short, long, parse(try_from_str = parse::parse_duration_from_str))]
         buckets: Option<Duration>,
 
+        /// Whether buckets start at the first data point or at the start of calendar
+        /// months, quarters or years, the latter needs buckets of whole months
+        #[structopt(long, possible_values = &BucketAlignment::variants(), case_insensitive = true, default_value = "Anchor")]
+        bucket_alignment: BucketAlignment,
+
         /// Instead of doing per data point, calculate between first and last
         #[structopt(short = "S", long)]
         sum: bool,
+
+        /// How averages are calculated: `cumulative` since the first data point, `simple`
+        /// over a window of last days or `exponential` with weights halving every half-life
+        #[structopt(short, long, possible_values = &AverageMethod::variants(), case_insensitive = true, default_value = "Cumulative")]
+        method: AverageMethod,
+
+        /// Window of the simple moving average
+        #[structopt(short, long, default_value = "3 months", parse(try_from_str = parse::parse_duration_from_str))]
+        window: Duration,
+
+        /// Half-life of the exponential moving average
+        #[structopt(long, default_value = "1 month", parse(try_from_str = parse::parse_duration_from_str))]
+        half_life: Duration,
+
+        #[structopt(flatten)]
+        real: RealOptions,
+
+        #[structopt(flatten)]
+        resample: ResampleOptions,
+
+        #[structopt(flatten)]
+        delta: DeltaOptions,
+    },
+    /// Calculate compound annual growth rate, time-weighted and money-weighted return
+    Growth {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Add growth of the total converted into this currency
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Exchange rate for date, pass `today` for Today date
+        #[structopt(short = "E", long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        exchange_rate_date: Option<NaiveDate>,
+
+        /// Start date - first data point >= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+
+        /// End date - first data point <= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        end_date: Option<NaiveDate>,
+
+        #[structopt(flatten)]
+        contributions: ContributionsOptions,
+    },
+    /// Split changes between entries into contributions, market growth and FX effect
+    Breakdown {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Convert into this currency, adds FX effect and per period totals
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Start date - first data point >= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+
+        /// End date - first data point <= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        end_date: Option<NaiveDate>,
+
+        #[structopt(flatten)]
+        contributions: ContributionsOptions,
+    },
+    /// Show how much of the income was saved in every period
+    SavingsRate {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Length of periods savings rate is calculated for
+        #[structopt(short, long, possible_values = &Frequency::variants(), case_insensitive = true, default_value = "Monthly")]
+        frequency: Frequency,
+
+        /// Convert savings and income into this currency and compare their totals
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Exchange rate for date, pass `today` for Today date
+        #[structopt(short = "E", long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        exchange_rate_date: Option<NaiveDate>,
+
+        /// Start date - first data point >= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+
+        /// End date - first data point <= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        end_date: Option<NaiveDate>,
+
+        /// Csv file in the same layout as input file with income earned per month,
+        /// `<input>.income.csv` is used if not passed
+        #[structopt(long, value_name = "FILE")]
+        income: Option<String>,
+    },
+    /// Show how many months savings would last and progress towards financial independence
+    Runway {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Monthly spending along with currency name, e.g. 2000GBP
+        #[structopt(short = "m", long)]
+        spending: parse::Value,
+
+        /// Yearly percentage of savings which can be safely withdrawn
+        #[structopt(short, long, default_value = "4")]
+        withdrawal_rate: f32,
+
+        /// Exchange rate for date, pass `today` for Today date
+        #[structopt(short = "E", long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        exchange_rate_date: Option<NaiveDate>,
+
+        /// Start date - saving rate is calculated from first data point >= than this date
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+    },
+    /// Calculate volatility, maximum drawdown and longest time under water
+    Risk {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Add risk of the total converted into this currency
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Exchange rate for date, pass `today` for Today date
+        #[structopt(short = "E", long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        exchange_rate_date: Option<NaiveDate>,
+
+        /// Start date - first data point >= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+
+        /// End date - first data point <= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        end_date: Option<NaiveDate>,
+
+        #[structopt(flatten)]
+        contributions: ContributionsOptions,
+
+        #[structopt(flatten)]
+        resample: ResampleOptions,
+    },
+    /// Project balances forward based on their history
+    Forecast {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// How far into the future balances should be projected
+        #[structopt(default_value = "1 year", parse(try_from_str = parse::parse_duration_from_str))]
+        horizon: Duration,
+
+        /// How trend is fitted to the history
+        #[structopt(short, long, possible_values = &ForecastMethod::variants(), case_insensitive = true, default_value = "Linear")]
+        method: ForecastMethod,
+
+        /// Currency in which should forecast be done, if not passed forecast every currency
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Exchange rate for date, pass `today` for Today date
+        #[structopt(short = "E", long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        exchange_rate_date: Option<NaiveDate>,
+
+        /// Start date - first data point >= than this date will be used
+        #[structopt(short, long, value_name = "YYYY-MM-DD", parse(try_from_str = parse::parse_date_from_str))]
+        start_date: Option<NaiveDate>,
+
+        /// Add bounds of prediction interval with this confidence, in percent
+        #[structopt(long, value_name = "PERCENT")]
+        confidence: Option<f32>,
+
+        /// Instead of projection show when balances are expected to reach this amount
+        #[structopt(short, long)]
+        target: Option<f32>,
     },
+    /// Run Monte Carlo simulation of future balances
+    Simulate {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// How far into the future balances should be simulated
+        #[structopt(default_value = "10 years", parse(try_from_str = parse::parse_duration_from_str))]
+        horizon: Duration,
+
+        /// Simulate total of all columns converted into this currency at latest rates,
+        /// if not passed every column is simulated separately
+        #[structopt(short, long, env = "SAVINGS_CALC_CURRENCY", parse(try_from_str = parse::parse_currency_from_str))]
+        currency: Option<parse::Currency>,
+
+        /// Yearly return and volatility in percent assumed for a column, e.g. USD:7:15,
+        /// columns without assumption have them estimated from history
+        #[structopt(short, long, value_name = "CURRENCY:MEAN:VOLATILITY")]
+        assumption: Vec<simulation::Assumption>,
+
+        /// Amount added to column every month along with currency name, e.g. 200GBP
+        #[structopt(short = "m", long)]
+        contribution: Vec<parse::Value>,
+
+        /// Number of simulations to run
+        #[structopt(short, long, default_value = "1000")]
+        runs: usize,
+
+        /// Seed of random numbers generator, the same seed gives the same results
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+
+        #[structopt(flatten)]
+        contributions: ContributionsOptions,
+    },
+    /// Track progress of savings goals stored next to input file
+    Goals {
+        #[structopt(subcommand)]
+        cmd: GoalsCommand,
+    },
+    /// Manage recurring changes, e.g. monthly contributions, stored next to input file
+    Recurring {
+        #[structopt(subcommand)]
+        cmd: RecurringCommand,
+    },
+    /// Add entries for recurring changes due since they were last applied up to today
+    ApplyRecurring {
+        #[structopt(flatten)]
+        data: DataOptions,
+
+        /// Do not write files, only show which entries would be added
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Show effective settings and where they come from
+    Config,
+}
+
+/// Walk through every column asking for its value at date, the last known value is
+/// the default.
+fn ask_values(records: &parse::Records, date: NaiveDate) -> Vec<parse::Value> {
+    if !atty::is(atty::Stream::Stdin) {
+        clap::Error::with_description(
+            "--interactive needs stdin to be a terminal",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit();
+    }
+    if records.currencies.is_empty() {
+        clap::Error::with_description(
+            "No columns to fill in, pass --value instead",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit();
+    }
+    let last = records.records.iter().rev().find(|r| r.date <= date);
+    records
+        .currencies
+        .iter()
+        .enumerate()
+        .map(|(i, currency)| {
+            let previous = last.map_or(0.0, |r| r.savings[i]);
+            let amount = Input::<f32>::new()
+                .with_prompt(currency.to_string())
+                .default(previous)
+                .interact()
+                .unwrap();
+            let digits = currency.minor_units();
+            eprintln!(
+                "  {:.*} -> {:.*} ({:+.*})",
+                digits,
+                previous,
+                digits,
+                amount,
+                digits,
+                amount - previous
+            );
+            parse::Value {
+                amount,
+                currency: currency.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Convert records and, if passed, contributions into a single `<currency> Total` column.
+async fn convert_total(
+    records: parse::Records,
+    contributions: Option<parse::Records>,
+    currency: parse::Currency,
+    exchange_rate_date: Option<NaiveDate>,
+    provider: &conversions::Provider,
+) -> (parse::Records, Option<parse::Records>) {
+    let total_currency = parse::Currency::label(format!("{} Total", currency));
+    let mut total =
+        conversions::get_conversions(records, currency.clone(), exchange_rate_date, provider)
+            .await
+            .unwrap();
+    total.currencies = vec![total_currency.clone()];
+    let total_contributions = match contributions {
+        Some(contributions) => {
+            let mut converted =
+                conversions::get_conversions(contributions, currency, exchange_rate_date, provider)
+                    .await
+                    .unwrap();
+            converted.currencies = vec![total_currency];
+            Some(converted)
+        }
+        None => None,
+    };
+    (total, total_contributions)
+}
+
+fn invalid_config(err: Box<dyn std::error::Error>) -> ! {
+    clap::Error::with_description(
+        &format!(
+            "Invalid config file {}: {}",
+            config::path().unwrap().display(),
+            err
+        ),
+        clap::ErrorKind::InvalidValue,
+    )
+    .exit()
 }
 
 #[tokio::main]
 async fn main() {
-    let opt = SavingsCalc::from_args();
+    let config = config::load().unwrap_or_else(|err| invalid_config(err));
+    let settings = config.settings();
+    let provider = config.provider();
+    // Checked only when needed so that a bad value doesn't stop e.g. `config`
+    let default_currency = || config.currency().unwrap_or_else(|err| invalid_config(err));
+    let default_period = || config.period().unwrap_or_else(|err| invalid_config(err));
+
+    let matches = SavingsCalc::clap().get_matches();
+    let mut opt = SavingsCalc::from_clap(&matches);
+    config
+        .apply(&matches, &mut opt.output)
+        .unwrap_or_else(|err| invalid_config(err));
     match opt.cmd {
-        Command::Show { records } => {
-            format::present_results(records, opt.format);
+        Command::Show {
+            data,
+            real,
+            resample,
+            delta,
+        } => {
+            let records = data.load(&config);
+            let records = resample.apply(real.apply(records));
+            format::present_results(delta.apply(records), &opt.output);
         }
         Command::Add {
-            mut records,
+            data,
             date,
             value,
+            interactive,
             dry_run,
+            anomaly,
+            prompt,
         } => {
+            let mut records = data.load(&config);
+            let value = if interactive {
+                ask_values(&records, date)
+            } else {
+                value
+            };
             let currencies: HashSet<_> = value.iter().map(|v| &v.currency).collect();
             if currencies.len() != value.len() {
                 clap::Error::value_validation_auto("Duplicated currency passed!".into()).exit();
@@ -124,107 +842,714 @@ async fn main() {
                 }
                 acc
             });
-            if !new_currencies.is_empty()
-                && !Confirm::new()
-                    .with_prompt(format!(
+            for currency in new_currencies.iter() {
+                if let Err(err) = records.check_currency(currency) {
+                    clap::Error::value_validation_auto(err.to_string()).exit();
+                }
+            }
+            if !new_currencies.is_empty() {
+                prompt.confirm(
+                    &format!(
                         "Currencies {:?} are new, are you sure you want to add them?",
                         new_currencies
-                    ))
-                    .interact()
-                    .unwrap()
-            {
-                clap::Error::with_description("Aborting!".into(), clap::ErrorKind::InvalidValue)
-                    .exit();
+                    ),
+                    Refusal::NewCurrency,
+                );
             }
-            if records.records.iter().any(|r| r.date == date) {
-                if !Confirm::new()
-                    .with_prompt(format!(
+            let existing = records.records.iter().any(|r| r.date == date);
+            if existing && !interactive {
+                prompt.confirm(
+                    &format!(
                         "Date {} already present in dataset, do you want to modify it?",
                         date
-                    ))
-                    .interact()
-                    .unwrap()
-                {
-                    clap::Error::with_description(
-                        "Aborting!".into(),
-                        clap::ErrorKind::InvalidValue,
-                    )
-                    .exit();
-                }
+                    ),
+                    Refusal::ExistingDate,
+                );
             }
             for value in value {
                 records.set_value(&value, date);
             }
+            // back-dated entry changes the delta of the following record too
+            let next = records
+                .records
+                .iter()
+                .find(|r| r.date > date)
+                .map(|r| r.date);
+            let anomalies: Vec<_> = anomaly
+                .find(&records)
+                .into_iter()
+                .filter(|a| a.date == date || Some(a.date) == next)
+                .collect();
+            if !anomalies.is_empty() {
+                format::present_report(format::anomalies_report(&anomalies), &opt.output);
+                if !interactive {
+                    prompt.confirm(
+                        "Changes above look suspicious, are you sure they are right?",
+                        Refusal::Suspicious,
+                    );
+                }
+            }
+            if interactive {
+                let (question, refusal) = if !anomalies.is_empty() {
+                    (" despite suspicious changes above", Refusal::Suspicious)
+                } else if existing {
+                    (" overwriting the existing one", Refusal::ExistingDate)
+                } else {
+                    ("", Refusal::Declined)
+                };
+                prompt.confirm(&format!("Write entry for {}{}?", date, question), refusal);
+            }
             if !dry_run {
-                parse::update_csv_file(&records);
+                parse::update_csv_file(&records).unwrap_or_else(|err| {
+                    clap::Error::with_description(
+                        &format!("{}: {}", records.filepath, err),
+                        clap::ErrorKind::Io,
+                    )
+                    .exit()
+                });
+            }
+            format::present_results(records, &opt.output);
+        }
+        Command::Validate { data, fix } => {
+            let records = data.path(&config);
+            let (parsed, issues) = parse::lint(&records).unwrap_or_else(|err| {
+                clap::Error::value_validation_auto(format!("{}: {}", records, err)).exit()
+            });
+            if !issues.is_empty() {
+                format::present_report(format::validation_report(&issues), &opt.output);
+            }
+            let fixable = issues.iter().all(|i| i.problem.fixable());
+            if fix && fixable {
+                if !issues.is_empty() {
+                    parse::update_csv_file(&parse::sort_and_merge(parsed)).unwrap();
+                }
+            } else if !issues.is_empty() {
+                clap::Error::with_description(
+                    &format!(
+                        "{} problems found{}",
+                        issues.len(),
+                        if fix {
+                            ", only unsorted and duplicated dates can be fixed"
+                        } else {
+                            ""
+                        }
+                    ),
+                    clap::ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+        }
+        Command::Config => {
+            let path = config::path().map_or("-".to_string(), |p| p.display().to_string());
+            format::present_report(format::config_report(&path, settings), &opt.output);
+        }
+        Command::Check { data, anomaly } => {
+            let records = data.load(&config);
+            let anomalies = anomaly.find(&records);
+            format::present_report(format::anomalies_report(&anomalies), &opt.output);
+            if !anomalies.is_empty() {
+                clap::Error::with_description(
+                    &format!("{} suspicious changes found", anomalies.len()),
+                    clap::ErrorKind::InvalidValue,
+                )
+                .exit();
             }
-            format::present_results(records, opt.format);
         }
         Command::Converse {
-            records,
+            data,
             date,
             currency,
             delta,
+            split,
+            fx_attribution,
+            real,
+            resample,
         } => {
-            let mut records = conversions::get_conversions(records, currency, date)
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency).unwrap_or_else(|| {
+                clap::Error::with_description(
+                    "No currency, pass it or set currency in the config file",
+                    clap::ErrorKind::MissingRequiredArgument,
+                )
+                .exit()
+            });
+            let records = resample.apply(records);
+            let rates = conversions::get_rates(&records, &currency, date, &provider)
                 .await
                 .unwrap();
-            if delta {
-                let deltas = records
-                    .records
-                    .iter()
-                    .enumerate()
-                    .skip(1)
-                    .map(|(i, s)| parse::Record {
-                        date: s.date,
-                        savings: vec![
-                            s.savings[0],
-                            s.savings[0] - records.records[i - 1].savings[0],
-                        ],
-                    })
-                    .collect();
-                records = parse::Records {
-                    currencies: vec![
-                        records.currencies.remove(0),
-                        parse::Currency("Delta".to_string()),
-                    ],
-                    records: deltas,
-                    filepath: records.filepath,
-                };
+            if fx_attribution {
+                let breakdown =
+                    statistics::calculate_breakdown(&records, None, Some((&currency, &rates)))
+                        .unwrap();
+                format::present_report(format::fx_report(breakdown), &opt.output);
+                return;
             }
+            let records = if split {
+                conversions::split_convert(records, &rates, currency)
+            } else {
+                conversions::convert(records, &rates, currency)
+            }
+            .unwrap();
+            let records = real.apply(records);
 
-            format::present_results(records, opt.format);
+            format::present_results(delta.apply(records), &opt.output);
         }
         Command::RollingAverage {
-            records,
+            data,
             currency,
             period,
             exchange_rate_date,
             start_date,
             end_date,
             buckets,
+            bucket_alignment,
             sum,
+            method,
+            window,
+            half_life,
+            real,
+            resample,
+            delta,
         } => {
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            let period = period
+                .or_else(default_period)
+                .unwrap_or_else(|| parse::parse_duration_from_str("1 month").unwrap());
+            if method != AverageMethod::Cumulative && (buckets.is_some() || sum) {
+                clap::Error::value_validation_auto(
+                    "Buckets and sum are supported only by cumulative averages!".to_string(),
+                )
+                .exit();
+            }
             if let Some(buckets) = buckets {
                 if buckets > period {
                     clap::Error::value_validation_auto(
                         "Buckets duration cannot be longer than period!".to_string(),
-                    );
+                    )
+                    .exit();
                 }
             }
 
+            let records = resample.apply(records);
             let records = if let Some(currency) = currency {
-                conversions::get_conversions(records, currency, exchange_rate_date)
+                conversions::get_conversions(records, currency, exchange_rate_date, &provider)
                     .await
                     .unwrap()
             } else {
                 records
             };
-            let averages = statistics::calculate_rolling_average(
-                records, period, sum, buckets, start_date, end_date,
+            let records = real.apply(records);
+            let averages = match method {
+                AverageMethod::Cumulative => statistics::calculate_rolling_average(
+                    records,
+                    period,
+                    sum,
+                    buckets.map(|buckets| (buckets, bucket_alignment.into())),
+                    start_date,
+                    end_date,
+                ),
+                AverageMethod::Simple => statistics::calculate_simple_moving_average(
+                    records, period, window, start_date, end_date,
+                ),
+                AverageMethod::Exponential => statistics::calculate_exponential_moving_average(
+                    records, period, half_life, start_date, end_date,
+                ),
+            }
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+            format::present_results(delta.apply(averages), &opt.output);
+        }
+        Command::Growth {
+            data,
+            currency,
+            exchange_rate_date,
+            start_date,
+            end_date,
+            contributions,
+        } => {
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            let contributions = contributions.load(&records);
+            let mut growth = statistics::calculate_growth(
+                records.clone(),
+                contributions.as_ref(),
+                start_date,
+                end_date,
+            )
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+
+            if let Some(currency) = currency {
+                let (total, total_contributions) = convert_total(
+                    records,
+                    contributions,
+                    currency,
+                    exchange_rate_date,
+                    &provider,
+                )
+                .await;
+                growth.extend(
+                    statistics::calculate_growth(
+                        total,
+                        total_contributions.as_ref(),
+                        start_date,
+                        end_date,
+                    )
+                    .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit()),
+                );
+            }
+            format::present_report(format::growth_report(growth), &opt.output);
+        }
+        Command::SavingsRate {
+            data,
+            frequency,
+            currency,
+            exchange_rate_date,
+            start_date,
+            end_date,
+            income,
+        } => {
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            let income = match parse::load_income(&records, income.as_deref())
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit())
+            {
+                Some(income) => income,
+                None => clap::Error::value_validation_auto(format!(
+                    "No income data, pass --income or create {}",
+                    parse::sidecar_path(&records.filepath, "income")
+                ))
+                .exit(),
+            };
+            let (records, income) = if let Some(currency) = currency {
+                let (total, total_income) = convert_total(
+                    records,
+                    Some(income),
+                    currency,
+                    exchange_rate_date,
+                    &provider,
+                )
+                .await;
+                (total, total_income.unwrap())
+            } else {
+                if !records
+                    .currencies
+                    .iter()
+                    .any(|c| income.currencies.contains(c))
+                {
+                    clap::Error::value_validation_auto(
+                        "Income and savings have no currency in common, pass --currency to \
+                         compare totals converted into one currency"
+                            .to_string(),
+                    )
+                    .exit();
+                }
+                (records, income)
+            };
+            let rates = statistics::calculate_savings_rate(
+                records,
+                &income,
+                frequency.into(),
+                start_date,
+                end_date,
             )
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+            format::present_report(format::savings_rate_report(rates), &opt.output);
+        }
+        Command::Runway {
+            data,
+            spending,
+            withdrawal_rate,
+            exchange_rate_date,
+            start_date,
+        } => {
+            let mut records = data.load(&config);
+            if let Err(err) = records.check_currency(&spending.currency) {
+                clap::Error::value_validation_auto(err.to_string()).exit();
+            }
+            if spending.amount <= 0.0 || withdrawal_rate <= 0.0 {
+                clap::Error::value_validation_auto(
+                    "Spending and withdrawal rate have to be positive!".to_string(),
+                )
+                .exit();
+            }
+            records.records = records.clone().records_newer_older_than(start_date, None);
+            if records.currencies != vec![spending.currency.clone()] {
+                records = conversions::get_conversions(
+                    records,
+                    spending.currency,
+                    exchange_rate_date,
+                    &provider,
+                )
+                .await
+                .unwrap();
+            }
+            let runway =
+                statistics::calculate_runway(records, spending.amount, withdrawal_rate).unwrap();
+            format::present_report(format::runway_report(runway), &opt.output);
+        }
+        Command::Risk {
+            data,
+            currency,
+            exchange_rate_date,
+            start_date,
+            end_date,
+            contributions,
+            resample,
+        } => {
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            let contributions = contributions.load(&records);
+            let records = resample.apply(records);
+            let mut risk = statistics::calculate_risk(
+                records.clone(),
+                contributions.as_ref(),
+                start_date,
+                end_date,
+            )
+            .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+
+            if let Some(currency) = currency {
+                let (total, total_contributions) = convert_total(
+                    records,
+                    contributions,
+                    currency,
+                    exchange_rate_date,
+                    &provider,
+                )
+                .await;
+                risk.extend(
+                    statistics::calculate_risk(
+                        total,
+                        total_contributions.as_ref(),
+                        start_date,
+                        end_date,
+                    )
+                    .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit()),
+                );
+            }
+            format::present_report(format::risk_report(risk), &opt.output);
+        }
+        Command::Breakdown {
+            data,
+            currency,
+            start_date,
+            end_date,
+            contributions,
+        } => {
+            let mut records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            let contributions = contributions.load(&records);
+            records.records = records
+                .clone()
+                .records_newer_older_than(start_date, end_date);
+
+            let breakdown = if let Some(currency) = currency {
+                let rates = conversions::get_rates(&records, &currency, None, &provider)
+                    .await
+                    .unwrap();
+                statistics::calculate_breakdown(
+                    &records,
+                    contributions.as_ref(),
+                    Some((&currency, &rates)),
+                )
+            } else {
+                statistics::calculate_breakdown(&records, contributions.as_ref(), None)
+            }
             .unwrap();
-            format::present_results(averages, opt.format);
+            format::present_report(format::breakdown_report(breakdown), &opt.output);
+        }
+        Command::Forecast {
+            data,
+            horizon,
+            method,
+            currency,
+            exchange_rate_date,
+            start_date,
+            confidence,
+            target,
+        } => {
+            let mut records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            if let Some(confidence) = confidence {
+                if confidence <= 0.0 || confidence >= 100.0 {
+                    clap::Error::value_validation_auto(
+                        "Confidence has to be between 0 and 100!".to_string(),
+                    )
+                    .exit();
+                }
+            }
+            records.records = records.clone().records_newer_older_than(start_date, None);
+            let records = if let Some(currency) = currency {
+                conversions::get_conversions(records, currency, exchange_rate_date, &provider)
+                    .await
+                    .unwrap()
+            } else {
+                records
+            };
+            let trends = statistics::fit_trends(&records, method.into())
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+            if let Some(target) = target {
+                format::present_report(
+                    format::target_report(&records, &trends, target),
+                    &opt.output,
+                );
+            } else {
+                let forecast =
+                    statistics::forecast(&records, &trends, horizon, confidence).unwrap();
+                format::present_forecast(records, forecast, &opt.output);
+            }
+        }
+        Command::Simulate {
+            data,
+            horizon,
+            currency,
+            assumption,
+            contribution,
+            runs,
+            seed,
+            contributions,
+        } => {
+            let records = data.load(&config);
+            let currency = currency.or_else(default_currency);
+            if records.records.is_empty() {
+                clap::Error::value_validation_auto("No records to simulate from!".to_string())
+                    .exit();
+            }
+            let contributions = contributions.load(&records);
+            let mut assumptions =
+                simulation::estimate_assumptions(&records, contributions.as_ref());
+            for assumption in assumption {
+                match assumptions
+                    .iter_mut()
+                    .find(|a| a.currency == assumption.currency)
+                {
+                    Some(a) => *a = assumption,
+                    None => clap::Error::value_validation_auto(format!(
+                        "There is no {} column!",
+                        assumption.currency
+                    ))
+                    .exit(),
+                }
+            }
+
+            if let Some(currency) = currency {
+                let date = records.records.last().map(|r| r.date);
+                let rates = conversions::get_rates(&records, &currency, date, &provider)
+                    .await
+                    .unwrap();
+                let latest = rates.last().cloned().unwrap_or_default();
+                let mut converted = records.clone();
+                for record in converted.records.iter_mut() {
+                    for (saving, rate) in record.savings.iter_mut().zip(latest.iter()) {
+                        *saving /= rate;
+                    }
+                }
+                let contribution: Vec<_> = contribution
+                    .into_iter()
+                    .map(|c| {
+                        let idx = records
+                            .currencies
+                            .iter()
+                            .position(|r| r == &c.currency)
+                            .unwrap_or_else(|| {
+                                clap::Error::value_validation_auto(format!(
+                                    "There is no {} column to contribute to!",
+                                    c.currency
+                                ))
+                                .exit()
+                            });
+                        parse::Value {
+                            amount: c.amount / latest[idx],
+                            currency: c.currency,
+                        }
+                    })
+                    .collect();
+                let label = format!("{} Total", currency);
+                let bands = simulation::simulate(
+                    &converted,
+                    &assumptions,
+                    &contribution,
+                    horizon,
+                    runs,
+                    seed,
+                    Some(&label),
+                )
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+                let history = parse::Records {
+                    records: converted
+                        .records
+                        .into_iter()
+                        .map(|r| parse::Record {
+                            date: r.date,
+                            savings: vec![r.savings.iter().sum()],
+                        })
+                        .collect(),
+                    currencies: vec![parse::Currency::label(label)],
+                    columns: vec![parse::Column::amount(&currency)],
+                    filepath: converted.filepath,
+                };
+                format::present_forecast(history, bands, &opt.output);
+            } else {
+                let bands = simulation::simulate(
+                    &records,
+                    &assumptions,
+                    &contribution,
+                    horizon,
+                    runs,
+                    seed,
+                    None,
+                )
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+                format::present_forecast(records, bands, &opt.output);
+            }
+        }
+        Command::Goals { cmd } => match cmd {
+            GoalsCommand::Show { data } => {
+                let records = data.load(&config);
+                let today = Utc::today().naive_local();
+                let mut progress = vec![];
+                for goal in goals::load_goals(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                }) {
+                    let mut total = goals::goal_records(&records, &goal).unwrap();
+                    if total.currencies != vec![goal.currency.clone()] {
+                        let date = total.records.last().map(|r| r.date);
+                        total = conversions::get_conversions(
+                            total,
+                            goal.currency.clone(),
+                            date,
+                            &provider,
+                        )
+                        .await
+                        .unwrap();
+                    }
+                    progress.push(goals::calculate_progress(goal, total, today).unwrap());
+                }
+                format::present_report(format::goals_report(progress), &opt.output);
+            }
+            GoalsCommand::Add {
+                data,
+                name,
+                value,
+                deadline,
+                account,
+            } => {
+                let records = data.load(&config);
+                let mut all_goals = goals::load_goals(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                });
+                if all_goals.iter().any(|g| g.name == name) {
+                    clap::Error::value_validation_auto(format!("Goal {} already exists!", name))
+                        .exit();
+                }
+                if value.amount <= 0.0 {
+                    clap::Error::value_validation_auto(
+                        "Goal amount has to be positive!".to_string(),
+                    )
+                    .exit();
+                }
+                if let Err(err) = parse::parse_currency_from_str(value.currency.as_str()) {
+                    clap::Error::value_validation_auto(err.to_string()).exit();
+                }
+                let goal = goals::Goal {
+                    name,
+                    amount: value.amount,
+                    currency: value.currency,
+                    deadline,
+                    accounts: account,
+                };
+                if let Err(err) = goals::goal_records(&records, &goal) {
+                    clap::Error::value_validation_auto(err.to_string()).exit();
+                }
+                all_goals.push(goal);
+                goals::save_goals(&records, &all_goals).unwrap();
+            }
+            GoalsCommand::Remove { data, name } => {
+                let records = data.load(&config);
+                let mut all_goals = goals::load_goals(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                });
+                let count = all_goals.len();
+                all_goals.retain(|g| g.name != name);
+                if all_goals.len() == count {
+                    clap::Error::value_validation_auto(format!("There is no goal {}!", name))
+                        .exit();
+                }
+                goals::save_goals(&records, &all_goals).unwrap();
+            }
+        },
+        Command::Recurring { cmd } => match cmd {
+            RecurringCommand::Show { data } => {
+                let records = data.load(&config);
+                let today = Utc::today().naive_local();
+                let rules = recurring::load_rules(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                });
+                format::present_report(format::recurring_report(&rules, today), &opt.output);
+            }
+            RecurringCommand::Add {
+                data,
+                name,
+                value,
+                day,
+                start,
+            } => {
+                let records = data.load(&config);
+                let mut rules = recurring::load_rules(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                });
+                if rules.iter().any(|r| r.name == name) {
+                    clap::Error::value_validation_auto(format!("Rule {} already exists!", name))
+                        .exit();
+                }
+                if !(1..=31).contains(&day) {
+                    clap::Error::value_validation_auto(
+                        "Day of month has to be between 1 and 31!".to_string(),
+                    )
+                    .exit();
+                }
+                if !records.currencies.contains(&value.currency) {
+                    clap::Error::value_validation_auto(format!(
+                        "Unknown column {}, add it first!",
+                        value.currency
+                    ))
+                    .exit();
+                }
+                rules.push(recurring::Rule {
+                    name,
+                    value,
+                    day,
+                    start,
+                    last_applied: None,
+                });
+                recurring::save_rules(&records, &rules).unwrap();
+            }
+            RecurringCommand::Remove { data, name } => {
+                let records = data.load(&config);
+                let mut rules = recurring::load_rules(&records).unwrap_or_else(|err| {
+                    clap::Error::value_validation_auto(err.to_string()).exit()
+                });
+                let count = rules.len();
+                rules.retain(|r| r.name != name);
+                if rules.len() == count {
+                    clap::Error::value_validation_auto(format!("There is no rule {}!", name))
+                        .exit();
+                }
+                recurring::save_rules(&records, &rules).unwrap();
+            }
+        },
+        Command::ApplyRecurring { data, dry_run } => {
+            let mut records = data.load(&config);
+            let today = Utc::today().naive_local();
+            let mut rules = recurring::load_rules(&records)
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err.to_string()).exit());
+            let entries = recurring::apply_rules(&mut records, &mut rules, today)
+                .unwrap_or_else(|err| clap::Error::value_validation_auto(err).exit());
+            format::present_report(format::recurring_entries_report(&entries), &opt.output);
+            if !dry_run && !entries.is_empty() {
+                parse::update_csv_file(&records).unwrap();
+                recurring::save_rules(&records, &rules).unwrap();
+            }
         }
     };
 }
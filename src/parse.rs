@@ -1,12 +1,17 @@
+use crate::iso4217;
 use chrono::{Duration, NaiveDate, Utc};
 use humantime;
 use simple_error::{bail, SimpleError, SimpleResult};
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
 use std::num::ParseFloatError;
+use std::path::Path;
 
-static DATE_FORMAT: &str = "%Y-%m-%d";
+pub static DATE_FORMAT: &str = "%Y-%m-%d";
 static TODAY: &str = "today";
+/// Characters an amount preceding currency name consists of
+static AMOUNT_CHARS: &str = "+-.0123456789";
 
 #[derive(Debug, Clone)]
 pub struct Record {
@@ -14,10 +19,67 @@ pub struct Record {
     pub savings: Vec<f32>,
 }
 
-#[derive(Debug)]
+/// Role of a projected column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Band {
+    Low,
+    Center,
+    High,
+}
+
+/// What values in a column mean
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnKind {
+    /// Amount of money, e.g. a balance read from the file or its conversion
+    Amount,
+    /// Change of amount against an earlier entry
+    Delta,
+    /// Change against an earlier entry in percent
+    DeltaPercent,
+    /// Projection continuing the column of history with given index
+    Projection(usize, Band),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub kind: ColumnKind,
+    /// Currency amounts are expressed in, None for percentages
+    pub unit: Option<Currency>,
+}
+
+impl Column {
+    /// Amounts of money in given currency
+    pub fn amount(unit: &Currency) -> Self {
+        Column {
+            kind: ColumnKind::Amount,
+            unit: Some(unit.clone()),
+        }
+    }
+
+    /// Column of the same unit with another meaning, percentages have no unit
+    pub fn derived(&self, kind: ColumnKind) -> Self {
+        Column {
+            kind,
+            unit: match kind {
+                ColumnKind::DeltaPercent => None,
+                _ => self.unit.clone(),
+            },
+        }
+    }
+
+    /// Whether values hold a change between entries rather than balances
+    pub fn is_delta(&self) -> bool {
+        matches!(self.kind, ColumnKind::Delta | ColumnKind::DeltaPercent)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Records {
     pub records: Vec<Record>,
+    /// Names of columns
     pub currencies: Vec<Currency>,
+    /// Meaning of every column, in the same order as names
+    pub columns: Vec<Column>,
     pub filepath: String,
 }
 
@@ -53,19 +115,21 @@ impl Records {
     /// currency amount and other currencies copied from previous record.
     /// If new currency is added fill previous dates with 0 and next datess with
     /// the same amount.
+    /// Dates before the first record start with 0 in other currencies.
     pub fn set_value(&mut self, val: &Value, date: NaiveDate) {
         let new_currency = !self.currencies.contains(&val.currency);
-        if new_currency {
-            self.currencies.push(val.currency.clone());
-            for record in self.records.iter_mut() {
-                record.savings.push(0.0);
+        let idx = match self.currencies.iter().position(|c| c == &val.currency) {
+            Some(idx) => idx,
+            None => {
+                self.currencies.push(val.currency.clone());
+                self.columns.push(Column::amount(&val.currency));
+                for record in self.records.iter_mut() {
+                    record.savings.push(0.0);
+                }
+                self.currencies.len() - 1
             }
-        }
-        let idx = self
-            .currencies
-            .iter()
-            .position(|c| c == &val.currency)
-            .unwrap();
+        };
+        let empty = vec![0.0; self.currencies.len()];
 
         let mut last = 0;
         for (i, record) in self.records.iter_mut().enumerate() {
@@ -73,7 +137,10 @@ impl Records {
                 record.savings[idx] = val.amount;
                 break;
             } else if record.date > date {
-                let mut savings = self.records[i - 1].savings.clone();
+                let mut savings = match i {
+                    0 => empty.clone(),
+                    _ => self.records[i - 1].savings.clone(),
+                };
                 savings[idx] = val.amount;
                 self.records.insert(i, Record { date, savings });
                 break;
@@ -82,7 +149,7 @@ impl Records {
         }
 
         if last == self.records.len() {
-            let mut savings = self.records[last - 1].savings.clone();
+            let mut savings = self.records.last().map_or(empty, |r| r.savings.clone());
             savings[idx] = val.amount;
             self.records.push(Record { date, savings });
         } else if new_currency {
@@ -92,10 +159,32 @@ impl Records {
             }
         }
     }
+
+    /// Check that a currency passed along with an amount is either ISO 4217 or
+    /// names a column or an allowed code, so that e.g. `2,000GBP` isn't read as 2
+    /// in a custom `,000GBP` column.
+    pub fn check_currency(&self, currency: &Currency) -> Result<(), Box<dyn Error>> {
+        if currency.is_iso()
+            || self.currencies.contains(currency)
+            || load_allowlist(&self.filepath)?.contains(currency)
+        {
+            return Ok(());
+        }
+        let suggestion = match iso4217::closest(&currency.0) {
+            Some(closest) => format!(" did you mean {}?", closest),
+            None => "".to_string(),
+        };
+        bail!(
+            "{} is not an ISO 4217 currency code,{} Custom codes can be allowed in {}",
+            currency,
+            suggestion,
+            sidecar_path(&self.filepath, "allowlist")
+        )
+    }
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
-pub struct Currency(pub String);
+pub struct Currency(String);
 
 impl fmt::Display for Currency {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -104,15 +193,44 @@ impl fmt::Display for Currency {
 }
 
 impl Currency {
-    fn new(value: &str) -> SimpleResult<Self> {
-        if value.len() != 3 {
-            Err(SimpleError::new("Currency code has to have 3 characters!"))
-        } else if value.chars().any(|c| !c.is_alphabetic()) {
-            Err(SimpleError::new(
-                "Currency code has to have only alphabetic characters!",
-            ))
-        } else {
-            Ok(Currency(value.to_uppercase()))
+    /// Number of digits after the decimal separator used by the currency
+    pub fn minor_units(&self) -> usize {
+        iso4217::minor_units(&self.0).unwrap_or(2)
+    }
+
+    pub fn symbol(&self) -> Option<&'static str> {
+        iso4217::symbol(&self.0)
+    }
+
+    /// Whether the code is an ISO 4217 currency rather than a custom asset
+    pub fn is_iso(&self) -> bool {
+        iso4217::is_known(&self.0)
+    }
+
+    /// Name of a derived column, e.g. `GBP Delta`, kept as it is
+    pub fn label(name: String) -> Self {
+        Currency(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Currency code or name of a custom asset, e.g. `Vanguard ISA`. Names can't
+    /// start with a digit or sign so they can follow an amount, 3 letter codes are
+    /// uppercased.
+    pub fn new(value: &str) -> SimpleResult<Self> {
+        let value = value.trim();
+        match value.chars().next() {
+            None => Err(SimpleError::new("Currency name can't be empty!")),
+            Some(c) if AMOUNT_CHARS.contains(c) => Err(SimpleError::new(format!(
+                "Currency name can't start with {:?}!",
+                c
+            ))),
+            _ if value.len() == 3 && value.chars().all(|c| c.is_alphabetic()) => {
+                Ok(Currency(value.to_uppercase()))
+            }
+            _ => Ok(Currency(value.to_string())),
         }
     }
 }
@@ -133,8 +251,11 @@ impl std::str::FromStr for Value {
     type Err = SimpleError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let currency = Currency::new(&value[value.len() - 3..value.len()])?;
-        let amount = &value[..value.len() - 3];
+        let split = value
+            .find(|c| !AMOUNT_CHARS.contains(c))
+            .ok_or_else(|| SimpleError::new("Currency name is missing!"))?;
+        let currency = Currency::new(&value[split..])?;
+        let amount = &value[..split];
         let amount: f32 = match amount.parse() {
             Ok(res) => res,
             Err(err) => {
@@ -149,39 +270,287 @@ impl std::str::FromStr for Value {
     }
 }
 
-pub fn parse_from_str(filepath: &str) -> Result<Records, Box<dyn Error>> {
-    let mut records = vec![];
-    let mut rdr = csv::Reader::from_path(filepath)?;
+/// Problem found in a records file.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    InvalidCurrency(String),
+    /// Code which is neither ISO 4217 nor allowed, along with the closest ISO one
+    UnknownCurrency(String, Option<&'static str>),
+    InvalidDate(String),
+    FutureDate(NaiveDate),
+    /// Date is earlier than the one in the previous row
+    Unsorted(NaiveDate),
+    Duplicate(NaiveDate),
+    Ragged {
+        expected: usize,
+        found: usize,
+    },
+    InvalidNumber(String),
+    NonFinite(String),
+}
+
+impl Problem {
+    /// Whether `sort_and_merge` gets rid of the problem
+    pub fn fixable(&self) -> bool {
+        matches!(self, Problem::Unsorted(_) | Problem::Duplicate(_))
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::InvalidCurrency(c) => write!(f, "{:?} is not a valid currency code", c),
+            Problem::UnknownCurrency(c, suggestion) => {
+                write!(f, "{:?} is not an ISO 4217 currency code, ", c)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "did you mean {}? ", suggestion)?;
+                }
+                write!(f, "custom codes have to be added to the allowlist")
+            }
+            Problem::InvalidDate(d) => write!(f, "{:?} is not a valid date", d),
+            Problem::FutureDate(d) => write!(f, "{} is in the future", d),
+            Problem::Unsorted(d) => write!(f, "{} is earlier than the previous date", d),
+            Problem::Duplicate(d) => write!(f, "{} is duplicated", d),
+            Problem::Ragged { expected, found } => {
+                write!(f, "expected {} columns, found {}", expected, found)
+            }
+            Problem::InvalidNumber(n) => write!(f, "{:?} is not a valid number", n),
+            Problem::NonFinite(n) => write!(f, "{:?} is not a finite number", n),
+        }
+    }
+}
+
+/// Problem along with its position, rows and columns are numbered from 1 the way
+/// spreadsheets do, the header being the first row.
+#[derive(Debug)]
+pub struct Issue {
+    pub row: usize,
+    pub column: Option<usize>,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "row {}, column {}: {}", self.row, column, self.problem),
+            None => write!(f, "row {}: {}", self.row, self.problem),
+        }
+    }
+}
+
+/// Parse records file collecting every problem found instead of stopping at the
+/// first one. Rows which can't be parsed are left out of returned records, the
+/// rest is kept in the file order.
+pub fn lint(filepath: &str) -> Result<(Records, Vec<Issue>), Box<dyn Error>> {
+    lint_file(filepath, None)
+}
+
+/// Lint either the records file or, if `records_path` is passed, a ledger belonging
+/// to it, e.g. CPI or income which can be known ahead.
+fn lint_file(
+    filepath: &str,
+    records_path: Option<&str>,
+) -> Result<(Records, Vec<Issue>), Box<dyn Error>> {
+    let mut issues = vec![];
+    let mut records: Vec<Record> = vec![];
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(filepath)?;
 
     let headers = rdr.headers()?.clone();
-    let currencies: Vec<_> = headers
-        .into_iter()
-        .skip(1)
-        .map(|c| Currency::new(c))
-        .collect::<SimpleResult<Vec<Currency>>>()?;
+    let allowlist = load_allowlist(records_path.unwrap_or(filepath))?;
+    let mut currencies = vec![];
+    for (i, header) in headers.iter().enumerate().skip(1) {
+        let problem = match Currency::new(header) {
+            Ok(currency) if currency.is_iso() || allowlist.contains(&currency) => {
+                currencies.push(currency);
+                continue;
+            }
+            Ok(currency) => {
+                let suggestion = iso4217::closest(&currency.0);
+                currencies.push(currency);
+                Problem::UnknownCurrency(header.to_string(), suggestion)
+            }
+            Err(_) => {
+                currencies.push(Currency(header.to_string()));
+                Problem::InvalidCurrency(header.to_string())
+            }
+        };
+        issues.push(Issue {
+            row: 1,
+            column: Some(i + 1),
+            problem,
+        });
+    }
+    let today = Utc::today().naive_local();
+    let mut dates = HashSet::new();
 
-    for result in rdr.records() {
-        let result_ = result?;
-        let mut result_iter = result_.into_iter();
-        let mut savings = Vec::new();
+    for (i, result) in rdr.records().enumerate() {
+        let row = result?;
+        let line = row.position().map_or(i + 2, |p| p.line() as usize);
+        let mut issue = |column: Option<usize>, problem| {
+            issues.push(Issue {
+                row: line,
+                column,
+                problem,
+            })
+        };
+
+        if row.len() != currencies.len() + 1 {
+            issue(
+                None,
+                Problem::Ragged {
+                    expected: currencies.len() + 1,
+                    found: row.len(),
+                },
+            );
+            continue;
+        }
+        let date = match NaiveDate::parse_from_str(&row[0], DATE_FORMAT) {
+            Ok(date) => date,
+            Err(_) => {
+                issue(Some(1), Problem::InvalidDate(row[0].to_string()));
+                continue;
+            }
+        };
+        if date > today && records_path.is_none() {
+            issue(Some(1), Problem::FutureDate(date));
+        }
+        if !dates.insert(date) {
+            issue(Some(1), Problem::Duplicate(date));
+        } else if matches!(records.last(), Some(previous) if date < previous.date) {
+            issue(Some(1), Problem::Unsorted(date));
+        }
+
+        let mut savings = vec![];
+        for (j, column) in row.iter().enumerate().skip(1) {
+            match column.trim().parse::<f32>() {
+                Ok(amount) if amount.is_finite() => savings.push(amount),
+                Ok(_) => issue(Some(j + 1), Problem::NonFinite(column.to_string())),
+                Err(_) => issue(Some(j + 1), Problem::InvalidNumber(column.to_string())),
+            }
+        }
+        if savings.len() == currencies.len() {
+            records.push(Record { date, savings });
+        }
+    }
+
+    Ok((
+        Records {
+            records,
+            columns: currencies.iter().map(Column::amount).collect(),
+            currencies,
+            filepath: filepath.to_string(),
+        },
+        issues,
+    ))
+}
+
+/// Sort records by date, if a date is duplicated the row which came later wins.
+pub fn sort_and_merge(mut records: Records) -> Records {
+    records.records.sort_by_key(|r| r.date);
+    let mut merged: Vec<Record> = vec![];
+    for record in records.records {
+        match merged.last_mut() {
+            Some(last) if last.date == record.date => *last = record,
+            _ => merged.push(record),
+        }
+    }
+    records.records = merged;
+    records
+}
+
+pub fn parse_from_str(filepath: &str) -> Result<Records, Box<dyn Error>> {
+    first_issue(lint(filepath)?)
+}
+
+/// Parse ledger belonging to the records file, e.g. contributions or CPI.
+pub fn parse_ledger_from_str(filepath: &str, records: &Records) -> Result<Records, Box<dyn Error>> {
+    first_issue(lint_file(filepath, Some(&records.filepath))?)
+}
+
+fn first_issue((records, issues): (Records, Vec<Issue>)) -> Result<Records, Box<dyn Error>> {
+    match issues.len() {
+        0 => Ok(records),
+        1 => bail!("{}", issues[0]),
+        n => bail!(
+            "{} and {} more problems, run `validate` to see all of them",
+            issues[0],
+            n - 1
+        ),
+    }
+}
 
-        let date = result_iter.next().expect("Empty row found!");
+/// Path of a companion file stored next to the records file, e.g. for `kind`
+/// `contributions` and `savings.csv` it is `savings.contributions.csv`.
+pub fn sidecar_path(filepath: &str, kind: &str) -> String {
+    let path = Path::new(filepath);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}.csv", stem, kind))
+        .to_string_lossy()
+        .into_owned()
+}
 
-        for (i, column) in result_iter.enumerate() {
-            assert!(currencies.len() > i);
-            let amount: f32 = column.parse()?;
-            savings.push(amount);
+/// Load records of given kind from `path` or, if not passed, stored next to the
+/// records file, if there are any.
+fn load_sidecar(
+    records: &Records,
+    kind: &str,
+    path: Option<&str>,
+) -> Result<Option<Records>, Box<dyn Error>> {
+    match path {
+        Some(path) => Ok(Some(parse_ledger_from_str(path, records)?)),
+        None => {
+            let path = sidecar_path(&records.filepath, kind);
+            if Path::new(&path).exists() {
+                Ok(Some(parse_ledger_from_str(&path, records)?))
+            } else {
+                Ok(None)
+            }
         }
-        records.push(Record {
-            date: NaiveDate::parse_from_str(date, DATE_FORMAT)?,
-            savings,
-        })
     }
-    Ok(Records {
-        records,
-        currencies,
-        filepath: filepath.to_string(),
-    })
+}
+
+/// Load ledger of contributions (positive) and withdrawals (negative) from `path` or
+/// stored next to the records file, if there is one.
+pub fn load_contributions(
+    records: &Records,
+    path: Option<&str>,
+) -> Result<Option<Records>, Box<dyn Error>> {
+    load_sidecar(records, "contributions", path)
+}
+
+/// Load CPI series per currency from `path` or stored next to the records file, if
+/// there is one.
+pub fn load_cpi(records: &Records, path: Option<&str>) -> Result<Option<Records>, Box<dyn Error>> {
+    load_sidecar(records, "cpi", path)
+}
+
+/// Load income earned per month in every currency from `path` or stored next to the
+/// records file, if there is one.
+pub fn load_income(
+    records: &Records,
+    path: Option<&str>,
+) -> Result<Option<Records>, Box<dyn Error>> {
+    load_sidecar(records, "income", path)
+}
+
+/// Load custom, non ISO 4217 codes allowed as columns of the records file, they
+/// are stored next to it, one per row under `Code` header.
+pub fn load_allowlist(filepath: &str) -> Result<Vec<Currency>, Box<dyn Error>> {
+    let path = sidecar_path(filepath, "allowlist");
+    if !Path::new(&path).exists() {
+        return Ok(vec![]);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut allowlist = vec![];
+    for result in rdr.records() {
+        allowlist.push(Currency::new(&result?[0])?);
+    }
+    Ok(allowlist)
 }
 
 pub fn update_csv_file(records: &Records) -> Result<(), Box<dyn Error>> {
@@ -211,8 +580,43 @@ pub fn parse_date_from_str(date: &str) -> Result<NaiveDate, Box<dyn Error>> {
     Ok(parsed)
 }
 
+/// Parse date which can be either in the past or in the future, e.g. start of a
+/// recurring change
+pub fn parse_any_date_from_str(date: &str) -> Result<NaiveDate, Box<dyn Error>> {
+    if date == TODAY {
+        return Ok(Utc::today().naive_local());
+    }
+    Ok(NaiveDate::parse_from_str(date, DATE_FORMAT)?)
+}
+
+/// Parse date which is expected to be in the future, e.g. a deadline
+pub fn parse_deadline_from_str(date: &str) -> Result<NaiveDate, Box<dyn Error>> {
+    let parsed = NaiveDate::parse_from_str(date, DATE_FORMAT)?;
+    if parsed <= Utc::today().naive_local() {
+        bail!("{:?} is not in the future!", parsed);
+    }
+    Ok(parsed)
+}
+
+/// Parse ISO 4217 currency code, e.g. one to convert into
 pub fn parse_currency_from_str(currency: &str) -> Result<Currency, Box<dyn Error>> {
-    Ok(Currency::new(currency)?)
+    let currency = Currency::new(currency)?;
+    if !currency.is_iso() {
+        match iso4217::closest(&currency.0) {
+            Some(suggestion) => bail!(
+                "{} is not an ISO 4217 currency code, did you mean {}?",
+                currency,
+                suggestion
+            ),
+            None => bail!("{} is not an ISO 4217 currency code!", currency),
+        }
+    }
+    Ok(currency)
+}
+
+/// Parse name of a column, custom asset codes are accepted as well
+pub fn parse_column_from_str(column: &str) -> Result<Currency, Box<dyn Error>> {
+    Ok(Currency::new(column)?)
 }
 
 pub fn parse_duration_from_str(duration: &str) -> Result<Duration, Box<dyn Error>> {
@@ -224,3 +628,111 @@ pub fn parse_duration_from_str(duration: &str) -> Result<Duration, Box<dyn Error
 
     Ok(duration)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn lint_str(content: &str) -> (Records, Vec<Issue>) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        lint(file.path().to_str().unwrap()).unwrap()
+    }
+
+    fn problems(issues: &[Issue]) -> Vec<(usize, &Problem)> {
+        issues.iter().map(|i| (i.row, &i.problem)).collect()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn valid_file_has_no_issues() {
+        let (records, issues) = lint_str("Date,GBP\n2020-01-31,10\n2020-02-29,20\n");
+        assert!(issues.is_empty());
+        assert_eq!(records.records.len(), 2);
+    }
+
+    #[test]
+    fn unsorted_rows_are_reported() {
+        let (records, issues) = lint_str("Date,GBP\n2020-02-29,20\n2020-01-31,10\n");
+        assert_eq!(
+            problems(&issues),
+            vec![(3, &Problem::Unsorted(date("2020-01-31")))]
+        );
+        assert!(issues[0].problem.fixable());
+        assert_eq!(records.records.len(), 2);
+    }
+
+    #[test]
+    fn duplicate_rows_are_reported() {
+        let (records, issues) = lint_str("Date,GBP\n2020-01-31,10\n2020-02-29,20\n2020-01-31,30\n");
+        assert_eq!(
+            problems(&issues),
+            vec![(4, &Problem::Duplicate(date("2020-01-31")))]
+        );
+        assert!(issues[0].problem.fixable());
+        assert_eq!(records.records.len(), 3);
+    }
+
+    #[test]
+    fn ragged_rows_are_reported_and_left_out() {
+        let (records, issues) =
+            lint_str("Date,GBP,USD\n2020-01-31,10,12\n2020-02-29,20\n2020-03-31,30,36,1\n");
+        assert_eq!(
+            problems(&issues),
+            vec![
+                (
+                    3,
+                    &Problem::Ragged {
+                        expected: 3,
+                        found: 2
+                    }
+                ),
+                (
+                    4,
+                    &Problem::Ragged {
+                        expected: 3,
+                        found: 4
+                    }
+                ),
+            ]
+        );
+        assert!(!issues[0].problem.fixable());
+        assert_eq!(records.records.len(), 1);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(lint("/nonexistent/savings.csv").is_err());
+    }
+
+    #[test]
+    fn malformed_amount_is_not_taken_for_a_custom_column() {
+        let (records, _) = lint_str("Date,GBP\n2020-01-31,10\n");
+        for value in &["2,000GBP", "1e5GBP", "2000GPB"] {
+            let value: Value = value.parse().unwrap();
+            assert!(records.check_currency(&value.currency).is_err());
+        }
+        let value: Value = "2000gbp".parse().unwrap();
+        assert!(records.check_currency(&value.currency).is_ok());
+    }
+
+    #[test]
+    fn sort_and_merge_keeps_later_duplicate() {
+        let (records, _) = lint_str("Date,GBP\n2020-02-29,20\n2020-01-31,10\n2020-02-29,25\n");
+        let merged = sort_and_merge(records);
+        let rows: Vec<_> = merged
+            .records
+            .iter()
+            .map(|r| (r.date, r.savings[0]))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(date("2020-01-31"), 10.0), (date("2020-02-29"), 25.0)]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::calendar;
+use crate::parse::{self, Currency, Records, Value};
+use chrono::{Datelike, NaiveDate};
+use simple_error::bail;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::Path;
+
+/// Change applied to a column on the same day of every month, e.g. a fixed pension
+/// contribution.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    /// Amount added to the column, negative for money taken out
+    pub value: Value,
+    /// Day of month, clamped to the length of shorter months
+    pub day: u32,
+    /// First date the rule can be applied on
+    pub start: NaiveDate,
+    /// Date the rule was last applied on
+    pub last_applied: Option<NaiveDate>,
+}
+
+impl Rule {
+    /// Dates on which the rule is due but was not applied yet, up to `until`.
+    pub fn due(&self, until: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = vec![];
+        let mut month = calendar::month_start(self.start);
+        while month <= until {
+            let days = calendar::days_in_month(month.year(), month.month());
+            let date = NaiveDate::from_ymd(month.year(), month.month(), self.day.min(days));
+            if date >= self.start
+                && date <= until
+                && self.last_applied.map_or(true, |last| date > last)
+            {
+                dates.push(date);
+            }
+            month = calendar::add_months(month, 1);
+        }
+        dates
+    }
+
+    /// First date the rule is going to be applied on after `after`.
+    pub fn next(&self, after: NaiveDate) -> NaiveDate {
+        let mut month = calendar::month_start(after);
+        loop {
+            let days = calendar::days_in_month(month.year(), month.month());
+            let date = NaiveDate::from_ymd(month.year(), month.month(), self.day.min(days));
+            if date > after && date >= self.start {
+                return date;
+            }
+            month = calendar::add_months(month, 1);
+        }
+    }
+}
+
+/// Entry created by applying a rule.
+#[derive(Debug)]
+pub struct Entry {
+    pub date: NaiveDate,
+    pub rule: String,
+    pub currency: Currency,
+    pub amount: f32,
+    /// Balance of the column after the change
+    pub balance: f32,
+}
+
+/// Rules are stored next to the records file, e.g. in `savings.recurring.csv`.
+pub fn load_rules(records: &Records) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let path = parse::sidecar_path(&records.filepath, "recurring");
+    if !Path::new(&path).exists() {
+        return Ok(vec![]);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut rules = vec![];
+    for result in rdr.records() {
+        let row = result?;
+        if row.len() != 6 {
+            bail!(
+                "Recurring rule row has to have 6 columns, got {}!",
+                row.len()
+            );
+        }
+        let day: u32 = row[3].parse()?;
+        if !(1..=31).contains(&day) {
+            bail!("Day of month has to be between 1 and 31, got {}!", day);
+        }
+        rules.push(Rule {
+            name: row[0].to_string(),
+            value: Value {
+                amount: row[1].parse()?,
+                currency: parse::parse_column_from_str(&row[2])?,
+            },
+            day,
+            start: NaiveDate::parse_from_str(&row[4], parse::DATE_FORMAT)?,
+            last_applied: match &row[5] {
+                "" => None,
+                date => Some(NaiveDate::parse_from_str(date, parse::DATE_FORMAT)?),
+            },
+        });
+    }
+    Ok(rules)
+}
+
+pub fn save_rules(records: &Records, rules: &[Rule]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(parse::sidecar_path(&records.filepath, "recurring"))?;
+    wtr.write_record(&["Name", "Amount", "Column", "Day", "Start", "Last applied"])?;
+    for rule in rules {
+        wtr.write_record(&[
+            rule.name.clone(),
+            rule.value.amount.to_string(),
+            rule.value.currency.to_string(),
+            rule.day.to_string(),
+            rule.start.format(parse::DATE_FORMAT).to_string(),
+            rule.last_applied
+                .map_or("".to_string(), |d| d.format(parse::DATE_FORMAT).to_string()),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Add every due change up to `until` to the balance the column had on that date,
+/// in date order, and mark rules as applied. Other columns are copied from the
+/// previous record as in `Records::set_value`. Changes due before the first record
+/// are skipped as there is no balance to add them to, changes due on a date which
+/// already has an entry are skipped too as the entry is taken to include them.
+pub fn apply_rules(
+    records: &mut Records,
+    rules: &mut [Rule],
+    until: NaiveDate,
+) -> Result<Vec<Entry>, String> {
+    let first = match records.records.first() {
+        Some(record) => record.date,
+        None => return Ok(vec![]),
+    };
+    let mut due = vec![];
+    for (i, rule) in rules.iter().enumerate() {
+        if !records.currencies.contains(&rule.value.currency) {
+            return Err(format!(
+                "Unknown column {} in recurring rule {}!",
+                rule.value.currency, rule.name
+            ));
+        }
+        due.extend(
+            rule.due(until)
+                .into_iter()
+                .filter(|date| *date >= first)
+                .map(|date| (date, i)),
+        );
+    }
+    due.sort();
+    let entered: HashSet<_> = records.records.iter().map(|r| r.date).collect();
+
+    let mut entries = vec![];
+    for (date, i) in due {
+        let rule = &mut rules[i];
+        if entered.contains(&date) {
+            rule.last_applied = Some(date);
+            continue;
+        }
+        let idx = records
+            .currencies
+            .iter()
+            .position(|c| c == &rule.value.currency)
+            .ok_or_else(|| format!("Unknown column {}!", rule.value.currency))?;
+        let previous = records
+            .records
+            .iter()
+            .rev()
+            .find(|r| r.date <= date)
+            .map(|r| r.savings[idx])
+            .ok_or_else(|| format!("No balance of {} on {}!", rule.value.currency, date))?;
+        let balance = previous + rule.value.amount;
+        records.set_value(
+            &Value {
+                amount: balance,
+                currency: rule.value.currency.clone(),
+            },
+            date,
+        );
+        rule.last_applied = Some(date);
+        entries.push(Entry {
+            date,
+            rule: rule.name.clone(),
+            currency: rule.value.currency.clone(),
+            amount: rule.value.amount,
+            balance,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{Column, Record};
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, parse::DATE_FORMAT).unwrap()
+    }
+
+    fn records(values: &[(&str, f32)]) -> Records {
+        let currency = parse::Currency::new("GBP").unwrap();
+        Records {
+            records: values
+                .iter()
+                .map(|(day, amount)| Record {
+                    date: date(day),
+                    savings: vec![*amount],
+                })
+                .collect(),
+            columns: vec![Column::amount(&currency)],
+            currencies: vec![currency],
+            filepath: String::new(),
+        }
+    }
+
+    fn rule(day: u32, start: &str) -> Rule {
+        Rule {
+            name: "pension".to_string(),
+            value: "100GBP".parse().unwrap(),
+            day,
+            start: date(start),
+            last_applied: None,
+        }
+    }
+
+    fn balances(records: &Records) -> Vec<(NaiveDate, f32)> {
+        records
+            .records
+            .iter()
+            .map(|r| (r.date, r.savings[0]))
+            .collect()
+    }
+
+    #[test]
+    fn rule_is_added_to_previous_balance() {
+        let mut records = records(&[("2020-01-01", 1000.0)]);
+        let mut rules = vec![rule(15, "2020-01-01")];
+        let entries = apply_rules(&mut records, &mut rules, date("2020-02-20")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            balances(&records),
+            vec![
+                (date("2020-01-01"), 1000.0),
+                (date("2020-01-15"), 1100.0),
+                (date("2020-02-15"), 1200.0),
+            ]
+        );
+        assert_eq!(rules[0].last_applied, Some(date("2020-02-15")));
+    }
+
+    #[test]
+    fn rule_due_on_existing_entry_is_skipped() {
+        let mut records = records(&[("2020-01-01", 1000.0), ("2020-01-15", 1100.0)]);
+        let mut rules = vec![rule(15, "2020-01-01")];
+        let entries = apply_rules(&mut records, &mut rules, date("2020-02-20")).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].date, date("2020-02-15"));
+        assert_eq!(
+            balances(&records),
+            vec![
+                (date("2020-01-01"), 1000.0),
+                (date("2020-01-15"), 1100.0),
+                (date("2020-02-15"), 1200.0),
+            ]
+        );
+        assert_eq!(rules[0].last_applied, Some(date("2020-02-15")));
+    }
+
+    #[test]
+    fn rule_due_only_on_existing_entry_is_marked_applied() {
+        let mut records = records(&[("2020-01-15", 1100.0)]);
+        let mut rules = vec![rule(15, "2020-01-01")];
+        let entries = apply_rules(&mut records, &mut rules, date("2020-01-20")).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(balances(&records), vec![(date("2020-01-15"), 1100.0)]);
+        assert_eq!(rules[0].last_applied, Some(date("2020-01-15")));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::calendar;
+use crate::parse::{Band, Column, ColumnKind, Currency, Record, Records, Value};
+use chrono::{Duration, NaiveDate};
+use float_ord::FloatOrd;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use simple_error::{bail, SimpleError};
+
+static DAYS_IN_YEAR: f64 = 365.25;
+
+/// Assumed yearly return and volatility of a column, both in percent.
+#[derive(Debug, Clone)]
+pub struct Assumption {
+    pub currency: Currency,
+    pub mean: f64,
+    pub volatility: f64,
+}
+
+impl std::str::FromStr for Assumption {
+    type Err = SimpleError;
+
+    /// Parse `<CURRENCY>:<MEAN>:<VOLATILITY>`, e.g. `USD:7:15`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = value.split(':').collect();
+        if parts.len() != 3 {
+            bail!("Assumption has to be in CURRENCY:MEAN:VOLATILITY format!");
+        }
+        let number = |part: &str| {
+            part.parse::<f64>()
+                .map_err(|_| SimpleError::new(format!("{} is not a valid float value!", part)))
+        };
+        let mean = number(parts[1])?;
+        if mean <= -100.0 {
+            bail!("Mean return has to be greater than -100%, got {}%!", mean);
+        }
+        Ok(Assumption {
+            currency: Currency::new(parts[0])?,
+            mean,
+            volatility: number(parts[2])?,
+        })
+    }
+}
+
+/// Estimate yearly return and volatility of every column from log returns between
+/// consecutive records, flows from contributions ledger are not counted as returns.
+pub fn estimate_assumptions(records: &Records, contributions: Option<&Records>) -> Vec<Assumption> {
+    records
+        .currencies
+        .iter()
+        .enumerate()
+        .map(|(i, currency)| {
+            let flow_idx =
+                contributions.and_then(|c| c.currencies.iter().position(|c| c == currency));
+            let returns: Vec<(f64, f64)> = records
+                .records
+                .windows(2)
+                .filter(|w| w[0].savings[i] > 0.0)
+                .filter_map(|w| {
+                    let flows: f32 = match (contributions, flow_idx) {
+                        (Some(contributions), Some(idx)) => contributions
+                            .records
+                            .iter()
+                            .filter(|r| r.date > w[0].date && r.date <= w[1].date)
+                            .map(|r| r.savings[idx])
+                            .sum(),
+                        _ => 0.0,
+                    };
+                    let growth = ((w[1].savings[i] - flows) / w[0].savings[i]) as f64;
+                    if growth > 0.0 {
+                        Some((growth.ln(), (w[1].date - w[0].date).num_days() as f64))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            let days: f64 = returns.iter().map(|(_, d)| d).sum();
+            let (drift, variance) = if days > 0.0 {
+                let drift = returns.iter().map(|(r, _)| r).sum::<f64>() / days;
+                let variance = returns
+                    .iter()
+                    .map(|(r, d)| (r - drift * d).powi(2))
+                    .sum::<f64>()
+                    / days;
+                (drift * DAYS_IN_YEAR, variance * DAYS_IN_YEAR)
+            } else {
+                (0.0, 0.0)
+            };
+            Assumption {
+                currency: currency.clone(),
+                mean: ((drift + variance / 2.0).exp() - 1.0) * 100.0,
+                volatility: variance.sqrt() * 100.0,
+            }
+        })
+        .collect()
+}
+
+/// Standard normal sample, Box-Muller transform.
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Simulate every column month by month from the last record with geometric
+/// brownian motion, adding monthly contributions. Returns `p10`, `p50` and `p90`
+/// percentiles for every month, either of every column or, if `total` label is
+/// passed, of the sum of all columns.
+pub fn simulate(
+    records: &Records,
+    assumptions: &[Assumption],
+    contributions: &[Value],
+    horizon: Duration,
+    runs: usize,
+    seed: u64,
+    total: Option<&str>,
+) -> Result<Records, String> {
+    let last = records
+        .records
+        .last()
+        .ok_or_else(|| "No records to simulate from!".to_string())?;
+    if runs == 0 {
+        return Err("At least one run is needed!".to_string());
+    }
+    if let Some(c) = contributions
+        .iter()
+        .find(|c| !records.currencies.contains(&c.currency))
+    {
+        return Err(format!(
+            "There is no {} column to contribute to!",
+            c.currency
+        ));
+    }
+    let mut dates = vec![last.date];
+    let mut months = 1;
+    while calendar::add_months(last.date, months) <= last.date + horizon {
+        dates.push(calendar::add_months(last.date, months));
+        months += 1;
+    }
+
+    let parameters = records
+        .currencies
+        .iter()
+        .map(|currency| {
+            let assumption = assumptions
+                .iter()
+                .find(|a| &a.currency == currency)
+                .ok_or_else(|| format!("No return assumption for {}!", currency))?;
+            let volatility = assumption.volatility / 100.0;
+            let drift = (1.0 + assumption.mean / 100.0).ln() - volatility.powi(2) / 2.0;
+            let contribution: f32 = contributions
+                .iter()
+                .filter(|c| &c.currency == currency)
+                .map(|c| c.amount)
+                .sum();
+            Ok((drift, volatility, contribution as f64))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let labels: Vec<String> = match total {
+        Some(label) => vec![label.to_string()],
+        None => records.currencies.iter().map(|c| c.to_string()).collect(),
+    };
+    // outcomes[step][series] holds results of all runs
+    let mut outcomes: Vec<Vec<Vec<f64>>> = dates
+        .iter()
+        .map(|_| labels.iter().map(|_| Vec::with_capacity(runs)).collect())
+        .collect();
+    let mut record = |step: usize, balances: &[f64]| {
+        if total.is_some() {
+            outcomes[step][0].push(balances.iter().sum());
+        } else {
+            for (series, balance) in outcomes[step].iter_mut().zip(balances.iter()) {
+                series.push(*balance);
+            }
+        }
+    };
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..runs {
+        let mut balances: Vec<f64> = last.savings.iter().map(|s| *s as f64).collect();
+        record(0, &balances);
+        for (step, window) in dates.windows(2).enumerate() {
+            let years = (window[1] - window[0]).num_days() as f64 / DAYS_IN_YEAR;
+            for (balance, (drift, volatility, contribution)) in
+                balances.iter_mut().zip(parameters.iter())
+            {
+                let shock = volatility * years.sqrt() * standard_normal(&mut rng);
+                *balance = *balance * (drift * years + shock).exp() + contribution;
+            }
+            record(step + 1, &balances);
+        }
+    }
+
+    let percentile = |values: &mut Vec<f64>, p: f64| {
+        values.sort_by_key(|v| FloatOrd(*v));
+        values[((values.len() - 1) as f64 * p).round() as usize] as f32
+    };
+    let bands = dates
+        .into_iter()
+        .zip(outcomes.iter_mut())
+        .map(|(date, series): (NaiveDate, _)| Record {
+            date,
+            savings: series
+                .iter_mut()
+                .flat_map(|values| {
+                    vec![
+                        percentile(values, 0.1),
+                        percentile(values, 0.5),
+                        percentile(values, 0.9),
+                    ]
+                })
+                .collect(),
+        })
+        .collect();
+
+    // total is in the unit shared by all columns, if there is one
+    let units: Vec<Option<Currency>> = match total {
+        Some(_) => {
+            let unit = records.columns.first().and_then(|c| c.unit.clone());
+            let shared = records.columns.iter().all(|c| c.unit == unit);
+            vec![if shared { unit } else { None }]
+        }
+        None => records.columns.iter().map(|c| c.unit.clone()).collect(),
+    };
+    let columns = units
+        .iter()
+        .enumerate()
+        .flat_map(|(i, unit)| {
+            [Band::Low, Band::Center, Band::High]
+                .iter()
+                .map(move |band| Column {
+                    kind: ColumnKind::Projection(i, *band),
+                    unit: unit.clone(),
+                })
+        })
+        .collect();
+
+    Ok(Records {
+        currencies: labels
+            .iter()
+            .flat_map(|label| {
+                ["p10", "p50", "p90"]
+                    .iter()
+                    .map(move |p| Currency::label(format!("{} {}", label, p)))
+            })
+            .collect(),
+        columns,
+        records: bands,
+        filepath: records.filepath.clone(),
+    })
+}
@@ -1,47 +1,168 @@
-use crate::parse::{Record, Records};
+use crate::calendar;
+use crate::parse::{Band, ColumnKind, Currency, Record, Records};
 use chrono::{Duration, NaiveDate};
+use float_ord::FloatOrd;
 
+/// Whether deltas are amounts or percentages
+#[derive(Debug, Clone, Copy)]
+pub enum DeltaMode {
+    Absolute,
+    Percent,
+}
+
+/// What a delta compares an entry with
+#[derive(Debug, Clone, Copy)]
+pub enum DeltaInterval {
+    Entry,
+    Month,
+    Quarter,
+    Year,
+}
+
+/// Length of a calendar period
+#[derive(Debug, Clone, Copy)]
+pub enum Frequency {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+/// How the value at the end of a period is picked
+#[derive(Debug, Clone, Copy)]
+pub enum ResampleMethod {
+    Last,
+    Interpolate,
+    Nearest,
+}
+
+/// Where consecutive buckets start
+#[derive(Debug, Clone, Copy)]
+pub enum BucketAlignment {
+    Anchor,
+    Calendar,
+}
+
+/// How a trend is fitted to history
+#[derive(Debug, Clone, Copy)]
+pub enum ForecastMethod {
+    Linear,
+    RollingAverage,
+}
+
+impl Frequency {
+    fn months(self) -> u32 {
+        match self {
+            Frequency::Monthly => 1,
+            Frequency::Quarterly => 3,
+            Frequency::Yearly => 12,
+        }
+    }
+}
+
+impl DeltaInterval {
+    fn months(self) -> Option<i32> {
+        match self {
+            DeltaInterval::Entry => None,
+            DeltaInterval::Month => Some(1),
+            DeltaInterval::Quarter => Some(3),
+            DeltaInterval::Year => Some(12),
+        }
+    }
+}
+
+/// Start dates of consecutive buckets covering dates from first to last. Anchored
+/// buckets start at the first date, calendar ones at the start of the period of
+/// whole months containing it, e.g. at the start of the quarter for 3 months.
+fn bucket_starts(
+    first: NaiveDate,
+    last: NaiveDate,
+    bucket: Duration,
+    alignment: BucketAlignment,
+) -> Result<Vec<NaiveDate>, String> {
+    let days = bucket.num_days() as f32;
+    let months = (days / calendar::DAYS_IN_MONTH).round() as u32;
+    let mut start = match alignment {
+        BucketAlignment::Anchor => first,
+        BucketAlignment::Calendar => {
+            if months == 0
+                || 12 % months != 0
+                || (days - months as f32 * calendar::DAYS_IN_MONTH).abs() > 3.0
+            {
+                return Err(
+                    "Calendar aligned buckets have to be 1, 2, 3, 4, 6 or 12 months long!"
+                        .to_string(),
+                );
+            }
+            calendar::month_start(calendar::add_months(
+                calendar::period_end(first, months),
+                1 - months as i32,
+            ))
+        }
+    };
+    let mut starts = vec![];
+    while start <= last {
+        starts.push(start);
+        start = match alignment {
+            BucketAlignment::Anchor => start + bucket,
+            BucketAlignment::Calendar => calendar::add_months(start, months as i32),
+        };
+    }
+    Ok(starts)
+}
+
+/// Average savings per period. With buckets records are grouped into consecutive
+/// buckets and averages are calculated within every bucket, starting from the last
+/// record before it so changes across boundaries are counted too. Buckets without
+/// any change to average are reported with NaN values.
 pub fn calculate_rolling_average(
     records: Records,
     period: Duration,
     sum: bool,
-    buckets: Option<Duration>,
+    buckets: Option<(Duration, BucketAlignment)>,
     start_date: Option<NaiveDate>,
     end_date: Option<NaiveDate>,
 ) -> Result<Records, String> {
     let currencies = records.currencies.clone();
+    let columns = records.columns.clone();
     let filepath = records.filepath.clone();
     let records = records.records_newer_older_than(start_date, end_date);
 
     let days = period.num_days() as f32;
 
-    let records_groups = if let Some(buckets) = buckets {
-        let mut end = records[0].date + buckets;
-        let mut result = vec![];
-        let mut current = vec![];
-
-        for record in records {
-            if record.date > end {
-                result.push(current);
-                current = vec![];
-                end = end + buckets;
-                continue;
+    let result = match (buckets, records.first(), records.last()) {
+        (Some((bucket, alignment)), Some(first), Some(last)) => {
+            let starts = bucket_starts(first.date, last.date, bucket, alignment)?;
+            let mut result = vec![];
+            for (i, start) in starts.iter().enumerate() {
+                let end = starts.get(i + 1);
+                let reference = records.iter().filter(|r| r.date < *start).last();
+                let bucket: Vec<Record> = reference
+                    .into_iter()
+                    .chain(
+                        records
+                            .iter()
+                            .filter(|r| r.date >= *start && end.map_or(true, |end| r.date < *end)),
+                    )
+                    .cloned()
+                    .collect();
+                let mut averages = calculate_records(bucket, days, sum);
+                match averages.first_mut() {
+                    Some(first) => first.date = *start,
+                    None => averages.push(Record {
+                        date: *start,
+                        savings: currencies.iter().map(|_| f32::NAN).collect(),
+                    }),
+                }
+                result.extend(averages);
             }
-            current.push(record);
+            result
         }
-        result
-    } else {
-        vec![records]
+        _ => calculate_records(records, days, sum),
     };
 
-    let result = records_groups
-        .into_iter()
-        .map(|records| calculate_records(records, days, sum))
-        .flatten()
-        .collect();
-
     Ok(Records {
         currencies,
+        columns,
         records: result,
         filepath,
     })
@@ -76,3 +197,1206 @@ fn calculate_records(mut records: Vec<Record>, days: f32, sum: bool) -> Vec<Reco
     }
     result
 }
+
+/// Values at given date linearly interpolated between surrounding records, None if
+/// the date is before the first record.
+fn interpolate(records: &[Record], date: NaiveDate) -> Option<Vec<f32>> {
+    let next = records.iter().position(|r| r.date >= date)?;
+    let after = &records[next];
+    if after.date == date {
+        return Some(after.savings.clone());
+    }
+    let before = records.get(next.checked_sub(1)?)?;
+    let ratio =
+        (date - before.date).num_days() as f32 / (after.date - before.date).num_days() as f32;
+    Some(
+        before
+            .savings
+            .iter()
+            .zip(after.savings.iter())
+            .map(|(b, a)| b + (a - b) * ratio)
+            .collect(),
+    )
+}
+
+/// Average savings per period over the window preceding every record, value at the
+/// start of the window is interpolated between records. Records with less history
+/// than the window are reported with NaN values.
+pub fn calculate_simple_moving_average(
+    records: Records,
+    period: Duration,
+    window: Duration,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Records, String> {
+    let currencies = records.currencies.clone();
+    let columns = records.columns.clone();
+    let filepath = records.filepath.clone();
+    let records = records.records_newer_older_than(start_date, end_date);
+    let ratio = period.num_days() as f32 / window.num_days() as f32;
+
+    let result = records
+        .iter()
+        .map(|record| {
+            let savings = match interpolate(&records, record.date - window) {
+                Some(before) => record
+                    .savings
+                    .iter()
+                    .zip(before.iter())
+                    .map(|(s, b)| (s - b) * ratio)
+                    .collect(),
+                None => currencies.iter().map(|_| f32::NAN).collect(),
+            };
+            Record {
+                date: record.date,
+                savings,
+            }
+        })
+        .collect();
+
+    Ok(Records {
+        currencies,
+        columns,
+        records: result,
+        filepath,
+    })
+}
+
+/// Exponentially weighted average of savings per period between consecutive records,
+/// weight of a change halves every half-life so recent changes dominate regardless
+/// of how irregular entries are.
+pub fn calculate_exponential_moving_average(
+    records: Records,
+    period: Duration,
+    half_life: Duration,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Records, String> {
+    let currencies = records.currencies.clone();
+    let columns = records.columns.clone();
+    let filepath = records.filepath.clone();
+    let records = records.records_newer_older_than(start_date, end_date);
+    let (period, half_life) = (period.num_days() as f32, half_life.num_days() as f32);
+
+    let mut result: Vec<Record> = vec![];
+    for w in records.windows(2) {
+        let days = (w[1].date - w[0].date).num_days() as f32;
+        let alpha = 1.0 - 0.5f32.powf(days / half_life);
+        let rates = w[1]
+            .savings
+            .iter()
+            .zip(w[0].savings.iter())
+            .map(|(current, previous)| (current - previous) / days * period);
+        let savings = match result.last() {
+            Some(last) => rates
+                .zip(last.savings.iter())
+                .map(|(rate, average)| average + alpha * (rate - average))
+                .collect(),
+            None => rates.collect(),
+        };
+        result.push(Record {
+            date: w[1].date,
+            savings,
+        });
+    }
+
+    Ok(Records {
+        currencies,
+        columns,
+        records: result,
+        filepath,
+    })
+}
+
+/// Add a delta column after every column. Delta is calculated against the previous
+/// entry or, for calendar intervals, against the last entry in the month interval
+/// ago or before it, e.g. for `Month` 15th of March is compared with the last entry
+/// from February. Entries without anything to compare against are skipped.
+pub fn calculate_deltas(records: Records, mode: DeltaMode, interval: DeltaInterval) -> Records {
+    let (suffix, kind) = match mode {
+        DeltaMode::Absolute => ("Delta", ColumnKind::Delta),
+        DeltaMode::Percent => ("Delta %", ColumnKind::DeltaPercent),
+    };
+    let currencies = records
+        .currencies
+        .iter()
+        .flat_map(|c| vec![c.clone(), Currency::label(format!("{} {}", c, suffix))])
+        .collect();
+    let columns = records
+        .columns
+        .iter()
+        .flat_map(|c| vec![c.clone(), c.derived(kind)])
+        .collect();
+
+    let mut result = vec![];
+    for (i, record) in records.records.iter().enumerate() {
+        let reference = match interval.months() {
+            None if i > 0 => Some(&records.records[i - 1]),
+            None => None,
+            Some(months) => {
+                let cutoff = calendar::add_months(calendar::month_start(record.date), 1 - months);
+                records.records[..i].iter().rev().find(|r| r.date < cutoff)
+            }
+        };
+        let reference = match reference {
+            Some(reference) => reference,
+            None => continue,
+        };
+
+        let savings = record
+            .savings
+            .iter()
+            .zip(reference.savings.iter())
+            .flat_map(|(current, previous)| {
+                let delta = match mode {
+                    DeltaMode::Absolute => current - previous,
+                    DeltaMode::Percent => (current - previous) / previous.abs() * 100.0,
+                };
+                vec![*current, delta]
+            })
+            .collect();
+        result.push(Record {
+            date: record.date,
+            savings,
+        });
+    }
+
+    Records {
+        currencies,
+        columns,
+        records: result,
+        filepath: records.filepath,
+    }
+}
+
+/// Build a regular series with one record at the end of every period between the
+/// first and the last record. Values are taken from the last known record, linearly
+/// interpolated between surrounding records or taken from the nearest record.
+/// The last period is stamped with the date of the last record and uses its value.
+pub fn resample(
+    records: Records,
+    frequency: Frequency,
+    method: ResampleMethod,
+) -> Result<Records, String> {
+    if records.records.is_empty() {
+        return Ok(records);
+    }
+    let months = frequency.months();
+    let first = &records.records[0];
+    let last = &records.records[records.records.len() - 1];
+
+    let mut result = vec![];
+    let mut date = calendar::period_end(first.date, months);
+    while date <= calendar::period_end(last.date, months) {
+        let next = records.records.iter().position(|r| r.date > date);
+        let savings = match next {
+            None => last.savings.clone(),
+            Some(0) => first.savings.clone(),
+            Some(i) => {
+                let (before, after) = (&records.records[i - 1], &records.records[i]);
+                match method {
+                    ResampleMethod::Last => before.savings.clone(),
+                    ResampleMethod::Nearest if after.date - date < date - before.date => {
+                        after.savings.clone()
+                    }
+                    ResampleMethod::Nearest => before.savings.clone(),
+                    ResampleMethod::Interpolate => {
+                        let ratio = (date - before.date).num_days() as f32
+                            / (after.date - before.date).num_days() as f32;
+                        before
+                            .savings
+                            .iter()
+                            .zip(after.savings.iter())
+                            .map(|(b, a)| b + (a - b) * ratio)
+                            .collect()
+                    }
+                }
+            }
+        };
+        // The last period is not over yet, stamp it with the last known date
+        // instead of the period end so rates are not requested for the future.
+        result.push(Record {
+            date: date.min(last.date),
+            savings,
+        });
+        date = calendar::period_end(date.succ(), months);
+    }
+
+    Ok(Records {
+        currencies: records.currencies,
+        columns: records.columns,
+        records: result,
+        filepath: records.filepath,
+    })
+}
+
+#[derive(Debug)]
+pub struct Growth {
+    pub currency: Currency,
+    pub unit: Option<Currency>,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub start_value: f32,
+    pub end_value: f32,
+    /// Compound annual growth rate of the balance, in percent
+    pub cagr: f32,
+    /// Time-weighted return over the whole period, in percent, known only if
+    /// contributions were passed
+    pub twr: Option<f32>,
+    /// Time-weighted return per year, in percent
+    pub annualized_twr: Option<f32>,
+    /// Money-weighted return per year, in percent, known only if contributions
+    /// were passed
+    pub xirr: Option<f32>,
+}
+
+/// Change of a column between two records split into money paid in, market
+/// growth and, for converted columns, currency revaluation.
+#[derive(Debug)]
+pub struct Breakdown {
+    pub date: NaiveDate,
+    pub currency: Currency,
+    pub unit: Option<Currency>,
+    pub change: f32,
+    pub contribution: f32,
+    pub growth: f32,
+    pub fx: f32,
+}
+
+/// Flows from contributions for given currency column which happened in (after, until].
+fn flows_between(
+    contributions: &Records,
+    currency: &Currency,
+    after: NaiveDate,
+    until: NaiveDate,
+) -> f32 {
+    match contributions.currencies.iter().position(|c| c == currency) {
+        Some(idx) => contributions
+            .records
+            .iter()
+            .filter(|r| r.date > after && r.date <= until)
+            .map(|r| r.savings[idx])
+            .sum(),
+        None => 0.0,
+    }
+}
+
+fn annualize(growth: f32, days: f32) -> f32 {
+    ((1.0 + growth).powf(365.25 / days) - 1.0) * 100.0
+}
+
+/// Calculate growth of every column between first record >= start date and last
+/// record <= end date. Contributions are records of money paid in (positive) or
+/// taken out (negative), if passed time-weighted return is calculated as well.
+pub fn calculate_growth(
+    records: Records,
+    contributions: Option<&Records>,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<Growth>, String> {
+    let currencies = records.currencies.clone();
+    let units: Vec<_> = records.columns.iter().map(|c| c.unit.clone()).collect();
+    let records = records.records_newer_older_than(start_date, end_date);
+    if records.len() < 2 {
+        return Err("At least two data points are needed to calculate growth!".to_string());
+    }
+    let first = &records[0];
+    let last = &records[records.len() - 1];
+    let days = (last.date - first.date).num_days() as f32;
+
+    Ok(currencies
+        .into_iter()
+        .enumerate()
+        .map(|(i, currency)| {
+            let (start_value, end_value) = (first.savings[i], last.savings[i]);
+            let xirr = contributions.and_then(|contributions| {
+                let mut flows = vec![(first.date, -start_value)];
+                if let Some(idx) = contributions.currencies.iter().position(|c| c == &currency) {
+                    flows.extend(
+                        contributions
+                            .records
+                            .iter()
+                            .filter(|r| r.date > first.date && r.date <= last.date)
+                            .map(|r| (r.date, -r.savings[idx])),
+                    );
+                }
+                flows.push((last.date, end_value));
+                xirr(&flows)
+            });
+            let twr = contributions.map(|contributions| {
+                records
+                    .windows(2)
+                    .filter(|w| w[0].savings[i] != 0.0)
+                    .map(|w| {
+                        let flows = flows_between(contributions, &currency, w[0].date, w[1].date);
+                        (w[1].savings[i] - flows) / w[0].savings[i]
+                    })
+                    .product::<f32>()
+                    - 1.0
+            });
+            Growth {
+                start: first.date,
+                end: last.date,
+                start_value,
+                end_value,
+                cagr: annualize(end_value / start_value - 1.0, days),
+                twr: twr.map(|twr| twr * 100.0),
+                annualized_twr: twr.map(|twr| annualize(twr, days)),
+                xirr: xirr.map(|xirr| xirr * 100.0),
+                unit: units[i].clone(),
+                currency,
+            }
+        })
+        .collect())
+}
+
+/// Annual rate of return making net present value of cash flows zero, flows are
+/// from the investor point of view, i.e. money paid in is negative. Solved with
+/// bisection as NPV is monotonic in rate for the usual deposits-then-balance flows.
+pub fn xirr(flows: &[(NaiveDate, f32)]) -> Option<f32> {
+    let start = flows.first()?.0;
+    let npv = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|(date, amount)| {
+                let years = (*date - start).num_days() as f64 / 365.0;
+                *amount as f64 / (1.0 + rate).powf(years)
+            })
+            .sum()
+    };
+
+    let (mut low, mut high) = (-0.9999, 100.0);
+    if npv(low).signum() == npv(high).signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid).signum() == npv(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(((low + high) / 2.0) as f32)
+}
+
+/// Split change of every column between consecutive records into contributions
+/// from the ledger, market growth and, if rates are passed, FX effect. With rates
+/// (as returned by `conversions::get_rates`) amounts are converted into
+/// `exchange_to`, flows and growth use rates from the end of period and a total of
+/// all columns is added for every period.
+pub fn calculate_breakdown(
+    records: &Records,
+    contributions: Option<&Records>,
+    rates: Option<(&Currency, &[Vec<f32>])>,
+) -> Result<Vec<Breakdown>, String> {
+    if let Some((_, rates)) = rates {
+        if rates.len() != records.records.len()
+            || rates.iter().any(|r| r.len() != records.currencies.len())
+        {
+            return Err("Exchange rates do not match records!".to_string());
+        }
+    }
+    let mut result = vec![];
+    for (i, window) in records.records.windows(2).enumerate() {
+        let (previous, current) = (&window[0], &window[1]);
+        let mut period = vec![];
+        for (j, currency) in records.currencies.iter().enumerate() {
+            let change = current.savings[j] - previous.savings[j];
+            let contribution = contributions.map_or(0.0, |contributions| {
+                flows_between(contributions, currency, previous.date, current.date)
+            });
+            let breakdown = match rates {
+                None => Breakdown {
+                    date: current.date,
+                    currency: currency.clone(),
+                    unit: records.columns[j].unit.clone(),
+                    change,
+                    contribution,
+                    growth: change - contribution,
+                    fx: 0.0,
+                },
+                Some((exchange_to, rates)) => {
+                    let (rate_before, rate_after) = (rates[i][j], rates[i + 1][j]);
+                    let fx = previous.savings[j] / rate_after - previous.savings[j] / rate_before;
+                    Breakdown {
+                        date: current.date,
+                        currency: Currency::label(format!("{} from {}", exchange_to, currency)),
+                        unit: Some(exchange_to.clone()),
+                        change: current.savings[j] / rate_after - previous.savings[j] / rate_before,
+                        contribution: contribution / rate_after,
+                        growth: (change - contribution) / rate_after,
+                        fx,
+                    }
+                }
+            };
+            period.push(breakdown);
+        }
+        if let Some((exchange_to, _)) = rates {
+            let total = Breakdown {
+                date: current.date,
+                currency: Currency::label(format!("{} Total", exchange_to)),
+                unit: Some(exchange_to.clone()),
+                change: period.iter().map(|b| b.change).sum(),
+                contribution: period.iter().map(|b| b.contribution).sum(),
+                growth: period.iter().map(|b| b.growth).sum(),
+                fx: period.iter().map(|b| b.fx).sum(),
+            };
+            period.push(total);
+        }
+        result.extend(period);
+    }
+    Ok(result)
+}
+
+/// Straight line fitted to the history of a single column.
+#[derive(Debug)]
+pub struct Trendline {
+    /// Date from which `days` are counted
+    pub origin: NaiveDate,
+    pub intercept: f32,
+    /// Change of value per day
+    pub slope: f32,
+    /// Standard deviation of history around the line
+    pub residual_std: f32,
+    count: f32,
+    mean_days: f32,
+    /// Sum of squared deviations of days from their mean
+    spread: f32,
+}
+
+impl Trendline {
+    pub fn value(&self, date: NaiveDate) -> f32 {
+        self.intercept + self.slope * (date - self.origin).num_days() as f32
+    }
+
+    /// Half width of the prediction interval at date for given normal quantile
+    pub fn margin(&self, date: NaiveDate, z: f32) -> f32 {
+        let days = (date - self.origin).num_days() as f32;
+        let leverage = if self.spread > 0.0 {
+            (days - self.mean_days).powi(2) / self.spread
+        } else {
+            0.0
+        };
+        z * self.residual_std * (1.0 + 1.0 / self.count + leverage).sqrt()
+    }
+
+    /// Date at which value growing along the line from `value` at `from` reaches
+    /// target, None if it never does
+    pub fn reaches(&self, target: f32, from: NaiveDate, value: f32) -> Option<NaiveDate> {
+        if value >= target {
+            return Some(from);
+        }
+        if self.slope <= 0.0 {
+            return None;
+        }
+        let days = ((target - value) / self.slope).ceil() as i64;
+        Some(from + Duration::days(days))
+    }
+}
+
+/// Fit a line to every column, either with least squares or going through the
+/// last record with slope of the rolling average between first and last record.
+pub fn fit_trends(records: &Records, method: ForecastMethod) -> Result<Vec<Trendline>, String> {
+    if records.records.len() < 2 {
+        return Err("At least two data points are needed to fit a trend!".to_string());
+    }
+    let origin = records.records[0].date;
+    let last = &records.records[records.records.len() - 1];
+    let days: Vec<f32> = records
+        .records
+        .iter()
+        .map(|r| (r.date - origin).num_days() as f32)
+        .collect();
+    let count = days.len() as f32;
+    let mean_days = days.iter().sum::<f32>() / count;
+    let spread: f32 = days.iter().map(|d| (d - mean_days).powi(2)).sum();
+
+    let slopes = match method {
+        ForecastMethod::RollingAverage => {
+            let averages = calculate_rolling_average(
+                records.clone(),
+                Duration::days(1),
+                true,
+                None,
+                None,
+                None,
+            )?;
+            averages.records[averages.records.len() - 1].savings.clone()
+        }
+        ForecastMethod::Linear => (0..records.currencies.len())
+            .map(|i| {
+                let mean = records.records.iter().map(|r| r.savings[i]).sum::<f32>() / count;
+                records
+                    .records
+                    .iter()
+                    .zip(days.iter())
+                    .map(|(r, d)| (d - mean_days) * (r.savings[i] - mean))
+                    .sum::<f32>()
+                    / spread
+            })
+            .collect(),
+    };
+
+    Ok(slopes
+        .into_iter()
+        .enumerate()
+        .map(|(i, slope)| {
+            let intercept = match method {
+                ForecastMethod::RollingAverage => {
+                    last.savings[i] - slope * (last.date - origin).num_days() as f32
+                }
+                ForecastMethod::Linear => {
+                    records.records.iter().map(|r| r.savings[i]).sum::<f32>() / count
+                        - slope * mean_days
+                }
+            };
+            let squares: f32 = records
+                .records
+                .iter()
+                .zip(days.iter())
+                .map(|(r, d)| (r.savings[i] - intercept - slope * d).powi(2))
+                .sum();
+            Trendline {
+                origin,
+                intercept,
+                slope,
+                residual_std: (squares / (count - 2.0).max(1.0)).sqrt(),
+                count,
+                mean_days,
+                spread,
+            }
+        })
+        .collect())
+}
+
+/// Quantile of the standard normal distribution, Acklam's rational approximation.
+pub fn normal_quantile(p: f32) -> f32 {
+    let p = p as f64;
+    let a = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    let b = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    let c = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    let d = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    let tail = |q: f64| {
+        (((((c[0] * q + c[1]) * q + c[2]) * q + c[3]) * q + c[4]) * q + c[5])
+            / ((((d[0] * q + d[1]) * q + d[2]) * q + d[3]) * q + 1.0)
+    };
+    let result = if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((a[0] * r + a[1]) * r + a[2]) * r + a[3]) * r + a[4]) * r + a[5]) * q
+            / (((((b[0] * r + b[1]) * r + b[2]) * r + b[3]) * r + b[4]) * r + 1.0)
+    };
+    result as f32
+}
+
+/// Project every column forward from the last record, one record per month up to
+/// the horizon. If confidence (in percent) is passed `Low` and `High` columns with
+/// prediction interval bounds are added after every column.
+pub fn forecast(
+    records: &Records,
+    trends: &[Trendline],
+    horizon: Duration,
+    confidence: Option<f32>,
+) -> Result<Records, String> {
+    let last = records
+        .records
+        .last()
+        .ok_or_else(|| "No records to forecast from!".to_string())?;
+    if trends.len() != records.currencies.len() {
+        return Err("Every column needs a trendline!".to_string());
+    }
+    let end = last.date + horizon;
+    let z = confidence.map(|c| normal_quantile(0.5 + c / 200.0));
+
+    let mut dates = vec![last.date];
+    let mut months = 1;
+    while calendar::add_months(last.date, months) < end {
+        dates.push(calendar::add_months(last.date, months));
+        months += 1;
+    }
+    dates.push(end);
+
+    let currencies = records
+        .currencies
+        .iter()
+        .flat_map(|c| match z {
+            Some(_) => vec![
+                c.clone(),
+                Currency::label(format!("{} Low", c)),
+                Currency::label(format!("{} High", c)),
+            ],
+            None => vec![c.clone()],
+        })
+        .collect();
+    let columns = records
+        .columns
+        .iter()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            let bands = match z {
+                Some(_) => vec![Band::Center, Band::Low, Band::High],
+                None => vec![Band::Center],
+            };
+            bands
+                .into_iter()
+                .map(move |band| c.derived(ColumnKind::Projection(i, band)))
+        })
+        .collect();
+
+    let projected = dates
+        .into_iter()
+        .map(|date| Record {
+            date,
+            savings: trends
+                .iter()
+                .enumerate()
+                .flat_map(|(i, trend)| {
+                    // start projection from the actual last value so it continues the history
+                    let value = if date == last.date {
+                        last.savings[i]
+                    } else {
+                        last.savings[i] + trend.value(date) - trend.value(last.date)
+                    };
+                    match z {
+                        Some(_) if date == last.date => vec![value, value, value],
+                        Some(z) => {
+                            let margin = trend.margin(date, z);
+                            vec![value, value - margin, value + margin]
+                        }
+                        None => vec![value],
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Records {
+        currencies,
+        columns,
+        records: projected,
+        filepath: records.filepath.clone(),
+    })
+}
+
+/// CPI of given column at date, linearly interpolated between known values and
+/// clamped to the first and last of them.
+fn cpi_at(cpi: &Records, idx: usize, date: NaiveDate) -> Option<f32> {
+    let first = cpi.records.first()?;
+    let last = cpi.records.last()?;
+    if date <= first.date {
+        return Some(first.savings[idx]);
+    }
+    if date >= last.date {
+        return Some(last.savings[idx]);
+    }
+    let next = cpi.records.iter().position(|r| r.date >= date)?;
+    let (before, after) = (&cpi.records[next - 1], &cpi.records[next]);
+    let ratio =
+        (date - before.date).num_days() as f32 / (after.date - before.date).num_days() as f32;
+    Some(before.savings[idx] + (after.savings[idx] - before.savings[idx]) * ratio)
+}
+
+/// Express every column in money of the base date, deflating it with CPI of the
+/// currency column amounts are in.
+pub fn deflate(records: Records, cpi: &Records, base: NaiveDate) -> Result<Records, String> {
+    let indexes = records
+        .currencies
+        .iter()
+        .zip(records.columns.iter())
+        .map(|(c, column)| {
+            column
+                .unit
+                .as_ref()
+                .and_then(|unit| cpi.currencies.iter().position(|cpi| cpi == unit))
+                .ok_or_else(|| format!("No CPI data for {}!", c))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let no_data = || "CPI data is empty!".to_string();
+
+    let mut result = vec![];
+    for record in records.records.iter() {
+        let savings = record
+            .savings
+            .iter()
+            .zip(indexes.iter())
+            .map(|(saving, idx)| {
+                let ratio = cpi_at(cpi, *idx, base).ok_or_else(no_data)?
+                    / cpi_at(cpi, *idx, record.date).ok_or_else(no_data)?;
+                Ok(saving * ratio)
+            })
+            .collect::<Result<_, String>>()?;
+        result.push(Record {
+            date: record.date,
+            savings,
+        });
+    }
+    Ok(Records {
+        currencies: records.currencies,
+        columns: records.columns,
+        records: result,
+        filepath: records.filepath,
+    })
+}
+
+/// Part of the income saved in a single period.
+#[derive(Debug)]
+pub struct SavingsRate {
+    pub date: NaiveDate,
+    pub currency: Currency,
+    pub unit: Option<Currency>,
+    pub income: f32,
+    pub saved: f32,
+    /// Saved amount as percentage of income, unknown without any income
+    pub rate: Option<f32>,
+}
+
+/// Compare change of every column with income in the same currency over calendar
+/// periods. Balances are taken at the end of every period, income earned within
+/// (end of previous period, end of period] counts towards it.
+pub fn calculate_savings_rate(
+    records: Records,
+    income: &Records,
+    frequency: Frequency,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<SavingsRate>, String> {
+    let columns: Vec<(usize, usize)> = records
+        .currencies
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            income
+                .currencies
+                .iter()
+                .position(|ic| ic == c)
+                .map(|j| (i, j))
+        })
+        .collect();
+    if columns.is_empty() {
+        return Err("Income and savings have no currency in common!".to_string());
+    }
+    let mut records = resample(records, frequency, ResampleMethod::Last)?;
+    records.records = records
+        .clone()
+        .records_newer_older_than(start_date, end_date);
+
+    let mut result = vec![];
+    for w in records.records.windows(2) {
+        for &(i, j) in columns.iter() {
+            let earned = income
+                .records
+                .iter()
+                .filter(|r| r.date > w[0].date && r.date <= w[1].date)
+                .map(|r| r.savings[j])
+                .sum();
+            let saved = w[1].savings[i] - w[0].savings[i];
+            result.push(SavingsRate {
+                date: w[1].date,
+                currency: records.currencies[i].clone(),
+                unit: records.columns[i].unit.clone(),
+                income: earned,
+                saved,
+                rate: if earned != 0.0 {
+                    Some(saved / earned * 100.0)
+                } else {
+                    None
+                },
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// How long savings would last and how far they are from financial independence.
+#[derive(Debug)]
+pub struct Runway {
+    pub currency: Currency,
+    pub unit: Option<Currency>,
+    pub date: NaiveDate,
+    pub balance: f32,
+    pub monthly_spending: f32,
+    pub months: f32,
+    /// Balance which covers yearly spending with the safe withdrawal rate
+    pub fi_number: f32,
+    pub progress: f32,
+    pub average_monthly: f32,
+    /// Date FI number is reached at the average monthly saving, unknown if never
+    pub fi_date: Option<NaiveDate>,
+}
+
+/// Average monthly change of the first column over the whole history, 0 if there
+/// is less than two records.
+pub fn monthly_average(total: Records) -> Result<f32, String> {
+    let averages = calculate_rolling_average(
+        total,
+        Duration::days(calendar::DAYS_IN_MONTH.round() as i64),
+        true,
+        None,
+        None,
+        None,
+    )?;
+    Ok(averages.records.last().map_or(0.0, |r| r.savings[0]))
+}
+
+/// Calculate runway of the latest balance and progress towards FI number, records
+/// have to be already converted into a single column in spending currency. Saving
+/// rate is the cumulative monthly average over the whole history.
+pub fn calculate_runway(
+    total: Records,
+    monthly_spending: f32,
+    withdrawal_rate: f32,
+) -> Result<Runway, String> {
+    let last = total
+        .records
+        .last()
+        .cloned()
+        .ok_or_else(|| "No records to calculate runway from!".to_string())?;
+    if total.currencies.len() != 1 {
+        return Err("Runway needs a single total column!".to_string());
+    }
+    let balance = last.savings[0];
+    let currency = total.currencies[0].clone();
+    let unit = total.columns[0].unit.clone();
+    let average_monthly = monthly_average(total)?;
+
+    let fi_number = monthly_spending * 12.0 / (withdrawal_rate / 100.0);
+    let missing = fi_number - balance;
+    let fi_date = if missing <= 0.0 {
+        Some(last.date)
+    } else if average_monthly > 0.0 {
+        let days = missing / average_monthly * calendar::DAYS_IN_MONTH;
+        Some(last.date + Duration::days(days.ceil() as i64))
+    } else {
+        None
+    };
+    Ok(Runway {
+        currency,
+        unit,
+        date: last.date,
+        balance,
+        monthly_spending,
+        months: balance / monthly_spending,
+        fi_number,
+        progress: balance / fi_number * 100.0,
+        average_monthly,
+        fi_date,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnomalyKind {
+    /// Change is many times bigger than the typical one
+    Jump,
+    /// Balance changed from positive to negative or the other way around
+    SignFlip,
+}
+
+/// Entry which looks suspicious compared to the history of its column.
+#[derive(Debug)]
+pub struct Anomaly {
+    pub date: NaiveDate,
+    pub currency: Currency,
+    pub unit: Option<Currency>,
+    pub previous: f32,
+    pub value: f32,
+    /// Median of absolute non-zero changes between preceding entries
+    pub typical: Option<f32>,
+    pub kind: AnomalyKind,
+}
+
+/// Find entries which changed more than `threshold` times the typical change of
+/// `window` preceding entries or flipped the sign. At least 3 preceding changes
+/// are needed to tell what a typical change is.
+pub fn find_anomalies(records: &Records, threshold: f32, window: usize) -> Vec<Anomaly> {
+    let mut result = vec![];
+    for (i, currency) in records.currencies.iter().enumerate() {
+        let values: Vec<f32> = records.records.iter().map(|r| r.savings[i]).collect();
+        let changes: Vec<f32> = values.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+        for (k, change) in changes.iter().enumerate() {
+            let (previous, value) = (values[k], values[k + 1]);
+            let mut recent: Vec<f32> = changes[k.saturating_sub(window)..k]
+                .iter()
+                .filter(|c| **c != 0.0)
+                .cloned()
+                .collect();
+            recent.sort_by_key(|c| FloatOrd(*c));
+            let typical = if recent.len() >= 3 {
+                Some(recent[recent.len() / 2])
+            } else {
+                None
+            };
+            let kind = if previous * value < 0.0 {
+                AnomalyKind::SignFlip
+            } else if typical.map_or(false, |typical| *change > typical * threshold) {
+                AnomalyKind::Jump
+            } else {
+                continue;
+            };
+            result.push(Anomaly {
+                date: records.records[k + 1].date,
+                currency: currency.clone(),
+                unit: records.columns[i].unit.clone(),
+                previous,
+                value,
+                typical,
+                kind,
+            });
+        }
+    }
+    result.sort_by_key(|a| a.date);
+    result
+}
+
+/// How bumpy the history of a column has been.
+#[derive(Debug)]
+pub struct Risk {
+    pub currency: Currency,
+    /// Number of periods between records returns were calculated for
+    pub periods: usize,
+    /// Standard deviation of period returns, in percent
+    pub volatility: f32,
+    /// Volatility scaled to a year using the average period length, in percent
+    pub annualized_volatility: f32,
+    /// Largest fall from a peak, in percent
+    pub max_drawdown: f32,
+    pub peak: Option<NaiveDate>,
+    pub trough: Option<NaiveDate>,
+    /// First date the peak was reached again, unknown if it still wasn't
+    pub recovery: Option<NaiveDate>,
+    /// Longest time between a peak and getting back to it (or the last record)
+    pub under_water: Duration,
+}
+
+/// Calculate volatility and drawdowns of every column between first record >=
+/// start date and last record <= end date. Period returns are calculated between
+/// consecutive records, if contributions are passed they are taken out of the
+/// returns and drawdowns are measured on the time-weighted value of the column.
+pub fn calculate_risk(
+    records: Records,
+    contributions: Option<&Records>,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<Risk>, String> {
+    let currencies = records.currencies.clone();
+    let records = records.records_newer_older_than(start_date, end_date);
+    if records.len() < 2 {
+        return Err("At least two data points are needed to calculate risk!".to_string());
+    }
+    let days = (records[records.len() - 1].date - records[0].date).num_days() as f32;
+
+    Ok(currencies
+        .into_iter()
+        .enumerate()
+        .map(|(i, currency)| {
+            let mut returns = vec![];
+            let start = match contributions {
+                Some(_) => 100.0,
+                None => records[0].savings[i],
+            };
+            let mut values = vec![(records[0].date, start)];
+            for w in records.windows(2) {
+                let value = values[values.len() - 1].1;
+                let ret = if w[0].savings[i] != 0.0 {
+                    let flows = contributions.map_or(0.0, |contributions| {
+                        flows_between(contributions, &currency, w[0].date, w[1].date)
+                    });
+                    let ret = (w[1].savings[i] - flows) / w[0].savings[i] - 1.0;
+                    returns.push(ret);
+                    ret
+                } else {
+                    0.0
+                };
+                let value = match contributions {
+                    Some(_) => value * (1.0 + ret),
+                    None => w[1].savings[i],
+                };
+                values.push((w[1].date, value));
+            }
+
+            let periods = returns.len();
+            let volatility = if periods > 1 {
+                let mean = returns.iter().sum::<f32>() / periods as f32;
+                (returns.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / (periods - 1) as f32)
+                    .sqrt()
+            } else {
+                0.0
+            };
+
+            let mut risk = Risk {
+                currency,
+                periods,
+                volatility: volatility * 100.0,
+                annualized_volatility: volatility
+                    * (365.25 * (records.len() - 1) as f32 / days).sqrt()
+                    * 100.0,
+                max_drawdown: 0.0,
+                peak: None,
+                trough: None,
+                recovery: None,
+                under_water: Duration::zero(),
+            };
+            let (mut peak_date, mut peak) = values[0];
+            let mut under_water = false;
+            for &(date, value) in values.iter() {
+                if value >= peak {
+                    if under_water && date - peak_date > risk.under_water {
+                        risk.under_water = date - peak_date;
+                    }
+                    if risk.peak == Some(peak_date) && risk.recovery.is_none() {
+                        risk.recovery = Some(date);
+                    }
+                    peak_date = date;
+                    peak = value;
+                    under_water = false;
+                } else {
+                    under_water = true;
+                    if peak > 0.0 {
+                        let drawdown = (value - peak) / peak * 100.0;
+                        if drawdown < risk.max_drawdown {
+                            risk.max_drawdown = drawdown;
+                            risk.peak = Some(peak_date);
+                            risk.trough = Some(date);
+                            risk.recovery = None;
+                        }
+                    }
+                }
+            }
+            let last = values[values.len() - 1].0;
+            if under_water && last - peak_date > risk.under_water {
+                risk.under_water = last - peak_date;
+            }
+            risk
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Column;
+
+    fn records(values: &[(&str, f32)]) -> Records {
+        let currency = Currency::new("GBP").unwrap();
+        Records {
+            records: values
+                .iter()
+                .map(|(day, amount)| Record {
+                    date: date(day),
+                    savings: vec![*amount],
+                })
+                .collect(),
+            columns: vec![Column::amount(&currency)],
+            currencies: vec![currency],
+            filepath: String::new(),
+        }
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn bucketed(records: Records, alignment: BucketAlignment) -> Vec<Record> {
+        calculate_rolling_average(
+            records,
+            Duration::days(30),
+            false,
+            Some((Duration::days(30), alignment)),
+            None,
+            None,
+        )
+        .unwrap()
+        .records
+    }
+
+    #[test]
+    fn record_on_bucket_boundary_is_kept() {
+        let result = bucketed(
+            records(&[
+                ("2020-01-01", 0.0),
+                ("2020-01-31", 30.0),
+                ("2020-03-01", 60.0),
+            ]),
+            BucketAlignment::Anchor,
+        );
+        let on_boundary: Vec<_> = result
+            .iter()
+            .filter(|r| r.date == date("2020-01-31"))
+            .collect();
+        assert_eq!(on_boundary.len(), 2);
+        assert_eq!(on_boundary[1].savings, vec![30.0]);
+    }
+
+    #[test]
+    fn final_bucket_is_reported() {
+        let result = bucketed(
+            records(&[
+                ("2020-01-01", 0.0),
+                ("2020-01-20", 10.0),
+                ("2020-02-10", 20.0),
+            ]),
+            BucketAlignment::Anchor,
+        );
+        assert!(result.iter().any(|r| r.date == date("2020-01-31")));
+        assert_eq!(result.last().unwrap().date, date("2020-02-10"));
+    }
+
+    #[test]
+    fn gap_spanning_several_buckets_is_reported_as_nan() {
+        let result = bucketed(
+            records(&[
+                ("2020-01-01", 0.0),
+                ("2020-01-10", 10.0),
+                ("2020-04-15", 100.0),
+            ]),
+            BucketAlignment::Calendar,
+        );
+        for month in &["2020-02-01", "2020-03-01"] {
+            let row = result.iter().find(|r| r.date == date(month)).unwrap();
+            assert!(row.savings[0].is_nan());
+        }
+        let last = result.last().unwrap();
+        assert_eq!(last.date, date("2020-04-15"));
+        assert!(last.savings[0].is_finite());
+    }
+
+    #[test]
+    fn empty_input_gives_empty_output() {
+        for alignment in &[BucketAlignment::Anchor, BucketAlignment::Calendar] {
+            assert!(bucketed(records(&[]), *alignment).is_empty());
+        }
+        let result =
+            calculate_rolling_average(records(&[]), Duration::days(30), true, None, None, None)
+                .unwrap();
+        assert!(result.records.is_empty());
+    }
+}